@@ -0,0 +1,384 @@
+use std::fmt;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use crate::matrix::Vector4;
+use crate::shape::{LightingProps,Shape,Interval,Hit,read_props_from_tokens};
+use crate::polygon::{SpatialProps,Polygon};
+use crate::mesh_shape::MeshShape;
+use crate::bounding_box::BoundingBox;
+
+//Finer tessellations than this would give each patch tens of thousands of triangles.
+const MAX_PATCH_LEVEL : usize = 128;
+//How far towards the middle of a patch to look for a normal where the patch pinches to a point.
+const PINCH_OFFSET : f64 = 0.001;
+//The most steps of Newton's method taken to move a hit from the triangles of a patch onto the patch itself.
+const NEWTON_STEPS : usize = 8;
+//Newton's method stops once a step moves u and v by less than this.
+const NEWTON_TOLERANCE : f64 = 0.0000000001;
+//How far outside of 0 to 1 the u and v of a hit may end up before it is taken to have left the patch.
+const PATCH_MARGIN : f64 = 0.000001;
+
+//A Bezier patch, with its control points in rows of constant u.
+struct BezierPatch{
+    u_degree: usize,
+    v_degree: usize,
+    control_points: Vec<Vector4>,
+}
+
+impl BezierPatch{
+    //The Bernstein polynomials of the given degree at t, which weigh each control point along one direction.
+    fn bernstein(degree: usize, t: f64) -> Vec<f64>{
+        let mut weights = vec![0.0; degree + 1];
+        weights[0] = 1.0;
+        for n in 1..=degree{
+            for i in (0..=n).rev(){
+                let from_left = if i > 0 { weights[i - 1] * t } else { 0.0 };
+                weights[i] = weights[i] * (1.0 - t) + from_left;
+            }
+        }
+        return weights;
+    }
+    //Sums the control points weighted by the products of the weights along u and v.
+    fn weighted_sum(&self, u_weights: &[f64], v_weights: &[f64]) -> Vector4{
+        let mut sum = Vector4::zero();
+        for i in 0..=self.u_degree{
+            for j in 0..=self.v_degree{
+                let mut weighted = self.control_points[i * (self.v_degree + 1) + j].clone();
+                weighted.force_vec();
+                weighted *= u_weights[i] * v_weights[j];
+                sum += &weighted;
+            }
+        }
+        return sum;
+    }
+    //The direction in which the patch moves as u increases. It is worked out from the differences between neighbouring
+    //rows of control points, so that it comes out as exactly zero where a whole row of control points meet.
+    fn along_u(&self, u: f64, v: f64) -> Vector4{
+        let (u_weights, v_weights) = (Self::bernstein(self.u_degree - 1, u), Self::bernstein(self.v_degree, v));
+        let mut sum = Vector4::zero();
+        for i in 0..self.u_degree{
+            for j in 0..=self.v_degree{
+                let mut difference = self.control_points[(i + 1) * (self.v_degree + 1) + j].clone();
+                difference -= &self.control_points[i * (self.v_degree + 1) + j];
+                difference *= self.u_degree as f64 * u_weights[i] * v_weights[j];
+                sum += &difference;
+            }
+        }
+        return sum;
+    }
+    //The direction in which the patch moves as v increases, worked out in the same way.
+    fn along_v(&self, u: f64, v: f64) -> Vector4{
+        let (u_weights, v_weights) = (Self::bernstein(self.u_degree, u), Self::bernstein(self.v_degree - 1, v));
+        let mut sum = Vector4::zero();
+        for i in 0..=self.u_degree{
+            for j in 0..self.v_degree{
+                let mut difference = self.control_points[i * (self.v_degree + 1) + j + 1].clone();
+                difference -= &self.control_points[i * (self.v_degree + 1) + j];
+                difference *= self.v_degree as f64 * u_weights[i] * v_weights[j];
+                sum += &difference;
+            }
+        }
+        return sum;
+    }
+    fn point_at(&self, u: f64, v: f64) -> Vector4{
+        let mut point = self.weighted_sum(&Self::bernstein(self.u_degree, u), &Self::bernstein(self.v_degree, v));
+        point.force_point();
+        return point;
+    }
+    //The normal of the patch, pointing along the cross product of the directions in which u and v increase.
+    fn normal_at(&self, u: f64, v: f64) -> Vector4{
+        return self.along_u(u, v).cross(&self.along_v(u, v));
+    }
+    //Where a patch pinches to a point, such as at the top of a lid, the normal is taken from just inside the patch instead.
+    fn shading_normal_at(&self, u: f64, v: f64) -> Vector4{
+        let normal = self.normal_at(u, v);
+        if normal.len_sq() > 0.0 {
+            return normal;
+        }
+        let nudge = |t: f64| t + (0.5 - t) * PINCH_OFFSET;
+        return self.normal_at(nudge(u), nudge(v));
+    }
+    //Finds where a ray meets the patch with Newton's method, starting from the u, v and t at which it met the triangles of the patch.
+    //Each step solves for the change in u, v and t that would close the gap between the patch and the ray if the patch were flat.
+    //Gives the t, u and v of the hit, or None if the steps do not settle on a point of the patch, such as where the patch pinches
+    //to a point or the ray only grazes it. The ray is given in the object space of the patch.
+    fn intersect(&self, origin: &Vector4, ray: &Vector4, start: (f64, f64, f64)) -> Option<(f64, f64, f64)>{
+        let (mut u, mut v, mut t) = start;
+        let mut backwards = ray.clone();
+        backwards *= -1.0;
+        for _step in 0..NEWTON_STEPS{
+            let mut ray_point = ray.clone();
+            ray_point *= t;
+            ray_point += origin;
+            let mut gap = self.point_at(u, v);
+            gap -= &ray_point;
+            gap.force_vec();
+
+            //Cramer's rule for the directions along u and v and back along the ray.
+            let (along_u, along_v) = (self.along_u(u, v), self.along_v(u, v));
+            let det = along_u.dot(&along_v.cross(&backwards));
+            if det == 0.0 || !det.is_finite() {
+                return None;
+            }
+            let du = -gap.dot(&along_v.cross(&backwards)) / det;
+            let dv = -along_u.dot(&gap.cross(&backwards)) / det;
+            let dt = -along_u.dot(&along_v.cross(&gap)) / det;
+            u += du;
+            v += dv;
+            t += dt;
+            if du.abs() + dv.abs() < NEWTON_TOLERANCE {
+                let inside = |param: f64| param > -PATCH_MARGIN && param < 1.0 + PATCH_MARGIN;
+                return match inside(u) && inside(v) {
+                    true => Some((t, u.clamp(0.0, 1.0), v.clamp(0.0, 1.0))),
+                    false => None,
+                };
+            }
+        }
+        return None;
+    }
+    //Splits the patch into a grid of level by level squares, each made of two triangles with the patch normals at their corners.
+    fn tessellate(&self, level: usize, spatial_props: &SpatialProps, polygons: &mut Vec<Polygon>){
+        let params: Vec<f64> = (0..=level).map(|step| step as f64 / level as f64).collect();
+        let mut points = Vec::<Vector4>::with_capacity((level + 1) * (level + 1));
+        let mut normals = Vec::<Vector4>::with_capacity((level + 1) * (level + 1));
+        for u in params.iter(){
+            for v in params.iter(){
+                points.push(self.point_at(*u, *v));
+                normals.push(self.shading_normal_at(*u, *v));
+            }
+        }
+
+        let index = |i: usize, j: usize| i * (level + 1) + j;
+        for i in 0..level{
+            for j in 0..level{
+                let corners = [index(i, j), index(i + 1, j), index(i + 1, j + 1), index(i, j + 1)];
+                for triangle in [[0, 1, 2], [0, 2, 3]]{
+                    let triangle = triangle.map(|corner| corners[corner]);
+                    let triangle_points = triangle.map(|corner| points[corner]);
+                    if Polygon::winding_normal(&triangle_points).len_sq() <= 0.0 {
+                        continue;
+                    }
+                    let mut polygon = Polygon::from_winding(triangle_points.to_vec(), spatial_props);
+                    if triangle.iter().all(|corner| normals[*corner].len_sq() > 0.0) {
+                        polygon.set_vertex_normals(triangle.map(|corner| normals[corner]).to_vec());
+                    }
+                    polygon.set_vertex_uvs(triangle.map(|corner| (params[corner / (level + 1)], params[corner % (level + 1)])).to_vec());
+                    polygons.push(polygon);
+                }
+            }
+        }
+    }
+}
+
+//Reads the numbers of a patch file one at a time, keeping track of the line each came from for error messages.
+struct PatchReader<'a>{
+    filename: &'a str,
+    tokens: Vec<(usize, &'a str)>,
+    next: usize,
+}
+
+impl<'a> PatchReader<'a>{
+    fn number(&mut self, what: &str) -> Result<f64, io::Error>{
+        let filename = self.filename;
+        let (line_number, token) = match self.tokens.get(self.next) {
+            None => return Err(Error::new(ErrorKind::Other, format!("{filename}: Patch file ended while reading {what}."))),
+            Some(token) => *token,
+        };
+        self.next += 1;
+        return match token.parse::<f64>() {
+            Err(_e) => Err(Error::new(ErrorKind::Other, format!("{filename}:{line_number}: Could not read {what} from {token}."))),
+            Ok(value) => Ok(value),
+        };
+    }
+    fn count(&mut self, what: &str) -> Result<usize, io::Error>{
+        let value = self.number(what)?;
+        if value < 0.0 || value.fract() != 0.0 {
+            let (line_number, _token) = self.tokens[self.next - 1];
+            return Err(Error::new(ErrorKind::Other, format!("{}:{line_number}: Expected a whole number for {what}, not {value}.", self.filename)));
+        }
+        return Ok(value as usize);
+    }
+}
+
+//Reads a patch file, which starts with the number of patches. Each patch follows as its degrees in u and v,
+//then its control points as x, y and z coordinates, with the points of each row of constant u listed together.
+fn load_patches(filename: &str) -> Result<Vec<BezierPatch>, io::Error>{
+    let contents = match std::fs::read_to_string(Path::new(filename)) {
+        Err(_e) => return Err(Error::new(ErrorKind::Other, format!("Couldn't open patch file {filename} for reading!"))),
+        Ok(contents) => contents,
+    };
+    let tokens = contents.lines().enumerate().flat_map(|(line_index, line)| line.split_whitespace().map(move |token| (line_index + 1, token))).collect();
+    let mut reader = PatchReader{filename, tokens, next: 0};
+
+    let patch_count = reader.count("the number of patches")?;
+    let mut patches = Vec::<BezierPatch>::new();
+    for _patch in 0..patch_count{
+        let u_degree = reader.count("the degree in u")?;
+        let v_degree = reader.count("the degree in v")?;
+        if u_degree == 0 || v_degree == 0 {
+            return Err(Error::new(ErrorKind::Other, format!("{filename}: Patch {} has a degree of 0.", patches.len() + 1)));
+        }
+        let point_count = (u_degree + 1) * (v_degree + 1);
+        let mut control_points = Vec::<Vector4>::new();
+        for _point in 0..point_count{
+            let x = reader.number("a control point")?;
+            let y = reader.number("a control point")?;
+            let z = reader.number("a control point")?;
+            control_points.push(Vector4::point(x, y, z));
+        }
+        patches.push(BezierPatch{u_degree, v_degree, control_points});
+    }
+    if patches.is_empty() {
+        return Err(Error::new(ErrorKind::Other, format!("{filename}: Patch file contains no patches.")));
+    }
+    return Ok(patches);
+}
+
+//A surface made of Bezier patches read from a patch file, such as the Utah teapot.
+//Each patch is split into a grid of triangles, which are used to find roughly where rays meet the patches.
+//Those hits are then moved onto the patches themselves with Newton's method, falling back to the triangles, which have
+//the normals of the patch at their corners, where it does not settle. Patch models are usually open, so the patches are lit from both sides.
+pub struct BezierSurface{
+    pub mesh_shape: MeshShape,
+    patches: Vec<BezierPatch>,
+    //The patch that each triangle of the mesh was made from.
+    face_patches: Vec<usize>,
+    pub level: usize,
+}
+
+impl BezierSurface{
+    //Reads a BEZIER line, which has the path of the patch file and the number of steps along each side of every patch after the name.
+    //The rest is laid out like a sphere.
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Result<Self, io::Error>{
+        let read_error = || Error::new(ErrorKind::Other, format!("Could not read Bezier surface from {}.", tokens.join(" ")));
+        if tokens.len() < 4 {
+            return Err(read_error());
+        }
+        let patch_file = tokens[2];
+        let level = match tokens[3].to_string().trim().parse::<usize>() {
+            Ok(level) if level > 0 && level <= MAX_PATCH_LEVEL => level,
+            _ => return Err(Error::new(ErrorKind::Other, format!("The tessellation level of a Bezier surface must be between 1 and {MAX_PATCH_LEVEL}: {}.", tokens.join(" ")))),
+        };
+        let mut shape_tokens = tokens.clone();
+        shape_tokens.drain(2..4);
+        let (spatial_props, lighting_props) = match read_props_from_tokens(&shape_tokens) {
+            None => return Err(read_error()),
+            Some(props) => props,
+        };
+
+        let patches = load_patches(patch_file)?;
+        let mut polygons = Vec::<Polygon>::with_capacity(2 * level * level * patches.len());
+        let mut face_patches = Vec::<usize>::with_capacity(polygons.capacity());
+        for (patch_index, patch) in patches.iter().enumerate(){
+            patch.tessellate(level, &spatial_props, &mut polygons);
+            face_patches.resize(polygons.len(), patch_index);
+        }
+        if polygons.is_empty() {
+            return Err(Error::new(ErrorKind::Other, format!("{patch_file}: Every patch in the file is degenerate.")));
+        }
+        let mesh_shape = MeshShape::new(spatial_props, lighting_props, polygons);
+        return Ok(Self{mesh_shape, patches, face_patches, level});
+    }
+}
+impl Shape for BezierSurface{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.mesh_shape.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.mesh_shape.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.mesh_shape.bounding_box();
+    }
+    fn is_solid(&self) -> bool{
+        return false;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        return self.mesh_shape.check_intervals(origin, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        let spatial_props = &self.mesh_shape.spatial_props;
+        let origin_prime = &spatial_props.inv_matrix * origin;
+        let ray_prime = &spatial_props.inv_matrix * ray;
+        let mut search_min = min;
+        while let Some(mut hit) = self.mesh_shape.check_collision(origin, ray, search_min, max) {
+            let patch = &self.patches[self.face_patches[hit.face_id]];
+            match patch.intersect(&origin_prime, &ray_prime, (hit.uv.0, hit.uv.1, hit.t)) {
+                //The triangle hit is kept where Newton's method does not settle.
+                None => (),
+                Some((t, u, v)) if t > min && t < max => {
+                    let mut col_pt = ray.clone();
+                    col_pt *= t;
+                    col_pt += origin;
+                    col_pt.force_point();
+                    let normal = patch.shading_normal_at(u, v).apply_inv_transpose(&spatial_props.inv_transp);
+                    let face_id = hit.face_id;
+                    hit = Hit::new(t, col_pt, normal, normal.dot(ray) < 0.0);
+                    hit.set_uv((u, v), &patch.along_u(u, v), spatial_props);
+                    hit.face_id = face_id;
+                },
+                //The patch is met outside of the range, such as back at the point a ray leaving the patch started from,
+                //where the triangles lie a little above or below the patch. Triangles further along the ray may still be hit.
+                Some(_) => {
+                    search_min = hit.t;
+                    continue;
+                },
+            }
+            if hit.normal.dot(ray) > 0.0 {
+                hit.normal *= -1.0;
+            }
+            return Some(hit);
+        }
+        return None;
+    }
+}
+impl fmt::Display for BezierSurface{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let spatial_props = &self.mesh_shape.spatial_props;
+        let lighting_props = &self.mesh_shape.lighting_props;
+        write!(f, "Bezier surface of {} patches at level {} with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.patches.len(), self.level, spatial_props.scale, spatial_props.r_x, spatial_props.r_y, spatial_props.r_z, lighting_props.color, spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", lighting_props.amb, lighting_props.diff, lighting_props.spec, lighting_props.refl, lighting_props.bright);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //A quadratic patch over the square from -1 to 1 in x and z whose middle control point is raised to 2, so its middle is at 0.5.
+    fn dome(level: usize) -> BezierSurface{
+        let path = std::env::temp_dir().join(format!("rusttracer_dome_{}_{}.bpt", level, std::process::id()));
+        std::fs::write(&path, "1\n2 2\n-1 0 -1 -1 0 0 -1 0 1\n0 0 -1 0 2 0 0 0 1\n1 0 -1 1 0 0 1 0 1\n").unwrap();
+        let line = format!("BEZIER dome {} {} 0 0 0 1 1 1 0 0 0 1 1 1 0.2 0.7 0.5 0 20", path.to_string_lossy(), level);
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let surface = BezierSurface::read_from_tokens(&tokens);
+        let _ = std::fs::remove_file(&path);
+        return surface.unwrap();
+    }
+
+    #[test]
+    fn hits_lie_on_the_patch(){
+        let surface = dome(1);
+        let down = Vector4::vec(0.0, -1.0, 0.0);
+        //The two triangles of level 1 are flat at y = 0, but the hit is moved up onto the patch.
+        let hit = surface.check_collision(&Vector4::point(0.0, 5.0, 0.0), &down, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.point.y() - 0.5).abs() < 0.000001);
+        assert!((hit.normal.x().abs() + hit.normal.z().abs()) < 0.000001 && hit.normal.y() > 0.0);
+
+        //Away from the middle, the hit matches where the patch is at the u and v of the hit.
+        let hit = surface.check_collision(&Vector4::point(0.3, 5.0, -0.6), &down, 0.0, f64::INFINITY).unwrap();
+        let on_patch = surface.patches[0].point_at(hit.uv.0, hit.uv.1);
+        assert!((&on_patch - &hit.point).len() < 0.000001);
+        assert!((hit.point.x() - 0.3).abs() < 0.000001 && (hit.point.z() + 0.6).abs() < 0.000001);
+    }
+
+    #[test]
+    fn rays_leaving_the_patch_do_not_hit_it_again(){
+        let surface = dome(1);
+        let hit = surface.check_collision(&Vector4::point(0.3, 5.0, -0.6), &Vector4::vec(0.0, -1.0, 0.0), 0.0, f64::INFINITY).unwrap();
+        assert!(surface.check_collision(&hit.point, &Vector4::vec(0.0, 1.0, 0.0), 0.0000001, f64::INFINITY).is_none());
+        //A ray carrying on down meets the triangles below the patch, but they lead back to where it started, so it hits nothing.
+        assert!(surface.check_collision(&hit.point, &Vector4::vec(0.0, -1.0, 0.0), 0.0000001, f64::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::matrix::{Vector4,Matrix4};
+
+//An axis aligned bounding box, used to quickly discard rays that cannot hit a shape.
+#[derive(Copy, Clone)]
+pub struct BoundingBox{
+    pub min: Vector4,
+    pub max: Vector4,
+}
+
+impl BoundingBox{
+    pub fn new(min: Vector4, max: Vector4) -> Self{
+        return Self{min, max};
+    }
+    //A box containing nothing, which can be grown to fit points or other boxes.
+    pub fn empty() -> Self{
+        let min = Vector4::point(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let max = Vector4::point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        return Self{min, max};
+    }
+    //A box containing all of space, for shapes such as planes that have no bounds.
+    pub fn infinite() -> Self{
+        let min = Vector4::point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        let max = Vector4::point(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        return Self{min, max};
+    }
+    //The box from (-1,-1,-1) to (1,1,1) that holds the unit shapes in object space.
+    pub fn unit() -> Self{
+        return Self::new(Vector4::point(-1.0, -1.0, -1.0), Vector4::point(1.0, 1.0, 1.0));
+    }
+    pub fn from_points(points: &[Vector4]) -> Self{
+        let mut bounds = Self::empty();
+        for point in points.iter(){
+            bounds.grow_point(point);
+        }
+        return bounds;
+    }
+    pub fn grow_point(&mut self, point: &Vector4){
+        for i in 0..3{
+            self.min.arr[i] = self.min.arr[i].min(point.arr[i]);
+            self.max.arr[i] = self.max.arr[i].max(point.arr[i]);
+        }
+    }
+    pub fn grow(&mut self, other: &BoundingBox){
+        for i in 0..3{
+            self.min.arr[i] = self.min.arr[i].min(other.min.arr[i]);
+            self.max.arr[i] = self.max.arr[i].max(other.max.arr[i]);
+        }
+    }
+    //The box holding the space shared by both boxes.
+    pub fn intersection(&self, other: &BoundingBox) -> Self{
+        let mut bounds = self.clone();
+        for i in 0..3{
+            bounds.min.arr[i] = bounds.min.arr[i].max(other.min.arr[i]);
+            bounds.max.arr[i] = bounds.max.arr[i].min(other.max.arr[i]);
+        }
+        return bounds;
+    }
+    pub fn is_empty(&self) -> bool{
+        return (0..3).any(|i| self.min.arr[i] > self.max.arr[i]);
+    }
+    pub fn is_finite(&self) -> bool{
+        return (0..3).all(|i| self.min.arr[i].is_finite() && self.max.arr[i].is_finite());
+    }
+    pub fn centroid(&self) -> Vector4{
+        let mut centroid = &self.min + &self.max;
+        centroid *= 0.5;
+        centroid.force_point();
+        return centroid;
+    }
+    pub fn surface_area(&self) -> f64{
+        if self.is_empty() {
+            return 0.0;
+        }
+        let mut extent = self.max.clone();
+        extent -= &self.min;
+        return 2.0 * (extent.x() * extent.y() + extent.y() * extent.z() + extent.z() * extent.x());
+    }
+    //Computes the box holding this box after it is transformed by the given matrix.
+    pub fn transform(&self, matrix: &Matrix4) -> Self{
+        let mut bounds = Self::empty();
+        for corner in 0..8{
+            let x = if corner & 1 == 0 { self.min.x() } else { self.max.x() };
+            let y = if corner & 2 == 0 { self.min.y() } else { self.max.y() };
+            let z = if corner & 4 == 0 { self.min.z() } else { self.max.z() };
+            bounds.grow_point(&(matrix * &Vector4::point(x, y, z)));
+        }
+        return bounds;
+    }
+    //Returns the range of t values for which origin + t * ray is inside the box, limited to (min, max).
+    //The reciprocal of each component of the ray is passed in so it can be computed once per ray.
+    pub fn check_collision(&self, origin: &Vector4, inv_ray: &Vector4, min: f64, max: f64) -> Option<(f64, f64)>{
+        let mut t_min = min;
+        let mut t_max = max;
+        for i in 0..3{
+            let t_1 = (self.min.arr[i] - origin.arr[i]) * inv_ray.arr[i];
+            let t_2 = (self.max.arr[i] - origin.arr[i]) * inv_ray.arr[i];
+            //f64::min and f64::max ignore the NaN produced when the origin lies on a slab of a box parallel to the ray.
+            t_min = t_min.max(t_1.min(t_2));
+            t_max = t_max.min(t_1.max(t_2));
+        }
+        if t_min > t_max {
+            return None;
+        }
+        return Some((t_min, t_max));
+    }
+}
@@ -0,0 +1,236 @@
+use crate::matrix::Vector4;
+use crate::bounding_box::BoundingBox;
+
+const NUM_BINS : usize = 16;
+const MAX_LEAF_SIZE : usize = 4;
+//The cost of visiting a node relative to the cost of checking a collision with one item.
+const TRAVERSAL_COST : f64 = 1.0;
+
+struct BvhNode{
+    bounds: BoundingBox,
+    //For a leaf, the first entry of Bvh::indices belonging to it.
+    //For an interior node, the index of its second child; the first child directly follows the node.
+    start: usize,
+    //The number of items in a leaf, or 0 for an interior node.
+    count: usize,
+}
+
+//A bounding volume hierarchy over a list of items, built using the surface area heuristic.
+//The items themselves are not stored, only their indices, so the same structure serves for
+//both the shapes in a scene and the polygons of a mesh.
+pub struct Bvh{
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+    //Items without finite bounds, such as planes, which are checked against every ray.
+    unbounded: Vec<usize>,
+}
+
+impl Bvh{
+    pub fn new(boxes: &[BoundingBox]) -> Self{
+        let (mut indices, unbounded): (Vec<usize>, Vec<usize>) = (0..boxes.len()).partition(|index| boxes[*index].is_finite());
+        let centroids: Vec<Vector4> = boxes.iter().map(|bounds| bounds.centroid()).collect();
+        let mut nodes = Vec::<BvhNode>::with_capacity(2 * indices.len());
+        if !indices.is_empty() {
+            Self::build(&mut nodes, &mut indices, 0, boxes, &centroids);
+        }
+        return Self{nodes, indices, unbounded};
+    }
+
+    fn build(nodes: &mut Vec<BvhNode>, indices: &mut [usize], start: usize, boxes: &[BoundingBox], centroids: &[Vector4]){
+        let mut bounds = BoundingBox::empty();
+        let mut centroid_bounds = BoundingBox::empty();
+        for index in indices.iter(){
+            bounds.grow(&boxes[*index]);
+            centroid_bounds.grow_point(&centroids[*index]);
+        }
+
+        let node_index = nodes.len();
+        nodes.push(BvhNode{bounds, start, count: indices.len()});
+        if indices.len() <= 1 {
+            return;
+        }
+
+        let split = match Self::find_split(indices, &bounds, &centroid_bounds, boxes, centroids) {
+            Some(split) => split,
+            None => return,
+        };
+        let (left, right) = indices.split_at_mut(split);
+        nodes[node_index].count = 0;
+        Self::build(nodes, left, start, boxes, centroids);
+        nodes[node_index].start = nodes.len();
+        Self::build(nodes, right, start + split, boxes, centroids);
+    }
+
+    //Partitions the indices along the axis and bin boundary with the lowest surface area heuristic cost.
+    //Returns the number of indices in the first half, or None if the node is better off as a leaf.
+    fn find_split(indices: &mut [usize], bounds: &BoundingBox, centroid_bounds: &BoundingBox, boxes: &[BoundingBox], centroids: &[Vector4]) -> Option<usize>{
+        let leaf_cost = indices.len() as f64;
+        let parent_area = bounds.surface_area();
+        let mut best: Option<(f64, usize, usize)> = None;
+
+        for axis in 0..3{
+            let axis_min = centroid_bounds.min.arr[axis];
+            let extent = centroid_bounds.max.arr[axis] - axis_min;
+            if extent <= 0.0 {
+                continue;
+            }
+
+            let mut bin_bounds = [BoundingBox::empty(); NUM_BINS];
+            let mut bin_counts = [0usize; NUM_BINS];
+            for index in indices.iter(){
+                let bin = Self::bin_of(centroids[*index].arr[axis], axis_min, extent);
+                bin_bounds[bin].grow(&boxes[*index]);
+                bin_counts[bin] += 1;
+            }
+
+            //Sweep from the right to find the area and count of everything after each boundary.
+            let mut right_areas = [0.0; NUM_BINS];
+            let mut right_counts = [0usize; NUM_BINS];
+            let mut right_bounds = BoundingBox::empty();
+            let mut right_count = 0;
+            for bin in (1..NUM_BINS).rev(){
+                right_bounds.grow(&bin_bounds[bin]);
+                right_count += bin_counts[bin];
+                right_areas[bin] = right_bounds.surface_area();
+                right_counts[bin] = right_count;
+            }
+
+            let mut left_bounds = BoundingBox::empty();
+            let mut left_count = 0;
+            for boundary in 1..NUM_BINS{
+                left_bounds.grow(&bin_bounds[boundary - 1]);
+                left_count += bin_counts[boundary - 1];
+                if left_count == 0 || right_counts[boundary] == 0 {
+                    continue;
+                }
+                let cost = TRAVERSAL_COST + (left_bounds.surface_area() * left_count as f64 + right_areas[boundary] * right_counts[boundary] as f64) / parent_area;
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, boundary));
+                }
+            }
+        }
+
+        let (cost, axis, boundary) = best?;
+        if cost >= leaf_cost && indices.len() <= MAX_LEAF_SIZE {
+            return None;
+        }
+
+        let axis_min = centroid_bounds.min.arr[axis];
+        let extent = centroid_bounds.max.arr[axis] - axis_min;
+        let mut split = 0;
+        for i in 0..indices.len(){
+            if Self::bin_of(centroids[indices[i]].arr[axis], axis_min, extent) < boundary {
+                indices.swap(i, split);
+                split += 1;
+            }
+        }
+        return Some(split);
+    }
+
+    //The box containing every item in the hierarchy.
+    pub fn bounds(&self) -> BoundingBox{
+        if !self.unbounded.is_empty() {
+            return BoundingBox::infinite();
+        }
+        return match self.nodes.first() {
+            None => BoundingBox::empty(),
+            Some(root) => root.bounds,
+        };
+    }
+
+    fn bin_of(value: f64, axis_min: f64, extent: f64) -> usize{
+        let bin = (NUM_BINS as f64 * (value - axis_min) / extent) as usize;
+        return bin.min(NUM_BINS - 1);
+    }
+
+    fn inverse_ray(ray: &Vector4) -> Vector4{
+        return Vector4::vec(1.0 / ray.x(), 1.0 / ray.y(), 1.0 / ray.z());
+    }
+
+    //Finds the closest item collision with t between min and max.
+    //check_item is given an item's index and the range of t to search, and returns the t and data of a collision.
+    pub fn closest_collision<T, F>(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64, mut check_item: F) -> Option<(f64, T)>
+    where F: FnMut(usize, f64, f64) -> Option<(f64, T)>{
+        let inv_ray = Self::inverse_ray(ray);
+        let mut lowest = max;
+        let mut col_data = None;
+        for index in self.unbounded.iter(){
+            if let Some((t, data)) = check_item(*index, min, lowest){
+                lowest = t;
+                col_data = Some((t, data));
+            }
+        }
+
+        let mut stack = Vec::<usize>::with_capacity(64);
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(node_index) = stack.pop(){
+            let node = &self.nodes[node_index];
+            if node.bounds.check_collision(origin, &inv_ray, min, lowest).is_none() {
+                continue;
+            }
+            if node.count > 0 {
+                for index in self.indices[node.start..node.start + node.count].iter(){
+                    if let Some((t, data)) = check_item(*index, min, lowest){
+                        lowest = t;
+                        col_data = Some((t, data));
+                    }
+                }
+                continue;
+            }
+
+            //Visit the nearer child first so that its collisions can cull the further child.
+            let (first, second) = (node_index + 1, node.start);
+            let first_t = self.nodes[first].bounds.check_collision(origin, &inv_ray, min, lowest);
+            let second_t = self.nodes[second].bounds.check_collision(origin, &inv_ray, min, lowest);
+            match (first_t, second_t) {
+                (Some((first_t, _)), Some((second_t, _))) => {
+                    if first_t <= second_t {
+                        stack.push(second);
+                        stack.push(first);
+                    }
+                    else {
+                        stack.push(first);
+                        stack.push(second);
+                    }
+                },
+                (Some(_), None) => stack.push(first),
+                (None, Some(_)) => stack.push(second),
+                (None, None) => {},
+            }
+        }
+        return col_data;
+    }
+
+    //Determines whether any item collides with the ray with t between min and max, stopping at the first one found.
+    pub fn any_collision<F>(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64, mut check_item: F) -> bool
+    where F: FnMut(usize, f64, f64) -> bool{
+        if self.unbounded.iter().any(|index| check_item(*index, min, max)) {
+            return true;
+        }
+        let inv_ray = Self::inverse_ray(ray);
+
+        let mut stack = Vec::<usize>::with_capacity(64);
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(node_index) = stack.pop(){
+            let node = &self.nodes[node_index];
+            if node.bounds.check_collision(origin, &inv_ray, min, max).is_none() {
+                continue;
+            }
+            if node.count > 0 {
+                for index in self.indices[node.start..node.start + node.count].iter(){
+                    if check_item(*index, min, max){
+                        return true;
+                    }
+                }
+                continue;
+            }
+            stack.push(node.start);
+            stack.push(node_index + 1);
+        }
+        return false;
+    }
+}
@@ -0,0 +1,195 @@
+use crate::matrix::Vector4;
+
+//Points closer than this fraction of the size of the point cloud to a plane are treated as lying on it.
+const PLANE_EPSILON : f64 = 0.000000001;
+
+//Finds the faces of the convex hull of a set of points, each given as its corners in counter-clockwise order
+//when viewed from outside of the hull. Points inside the hull or in the middle of its faces and edges are left out.
+//Returns None if the points do not enclose a volume.
+pub fn hull_faces(points: &[Vector4]) -> Option<Vec<Vec<Vector4>>>{
+    let mut unique = Vec::<Vector4>::with_capacity(points.len());
+    for point in points.iter(){
+        if !unique.iter().any(|other| (0..3).all(|i| other.arr[i] == point.arr[i])) {
+            unique.push(*point);
+        }
+    }
+    let points = unique;
+    if points.len() < 4 {
+        return None;
+    }
+
+    let mut size: f64 = 0.0;
+    for point in points.iter(){
+        let mut offset = point.clone();
+        offset -= &points[0];
+        size = size.max(offset.len());
+    }
+    let epsilon = PLANE_EPSILON * size;
+
+    //Each plane through three of the points with every point on or behind it holds a face.
+    let mut faces = Vec::<Vec<Vector4>>::new();
+    let mut face_members = Vec::<Vec<usize>>::new();
+    for i in 0..points.len(){
+        for j in (i + 1)..points.len(){
+            for k in (j + 1)..points.len(){
+                if face_members.iter().any(|members| members.contains(&i) && members.contains(&j) && members.contains(&k)) {
+                    continue;
+                }
+                let mut ab = points[j].clone();
+                ab -= &points[i];
+                let mut ac = points[k].clone();
+                ac -= &points[i];
+                let mut normal = ab.cross(&ac);
+                if normal.len() <= epsilon * size {
+                    continue;
+                }
+                normal.normalize();
+
+                let offset = normal.dot(&points[i]);
+                let (mut above, mut below) = (false, false);
+                let mut members = Vec::<usize>::new();
+                for (index, point) in points.iter().enumerate(){
+                    let distance = normal.dot(point) - offset;
+                    if distance > epsilon {
+                        above = true;
+                    }
+                    else if distance < -epsilon {
+                        below = true;
+                    }
+                    else {
+                        members.push(index);
+                    }
+                }
+                if above && below {
+                    continue;
+                }
+                if !above && !below {
+                    //Every point lies in one plane.
+                    return None;
+                }
+                if above {
+                    normal *= -1.0;
+                }
+                faces.push(order_face(&members.iter().map(|index| points[*index]).collect::<Vec<Vector4>>(), &normal, epsilon));
+                face_members.push(members);
+            }
+        }
+    }
+    return Some(faces);
+}
+
+//Sorts the points of a face counter-clockwise around its outward normal, keeping only its corners
+//and leaving out those in its middle or along its edges.
+fn order_face(points: &[Vector4], normal: &Vector4, epsilon: f64) -> Vec<Vector4>{
+    let mut center = Vector4::zero();
+    for point in points.iter(){
+        center += point;
+    }
+    center *= 1.0 / points.len() as f64;
+    center.force_point();
+
+    //Two axes in the plane of the face. The first points at the member farthest from the center,
+    //since a member may lie at the center itself.
+    let mut u_axis = Vector4::zero();
+    for point in points.iter(){
+        let offset = point - &center;
+        if offset.len_sq() > u_axis.len_sq() {
+            u_axis = offset;
+        }
+    }
+    u_axis.force_vec();
+    u_axis.normalize();
+    let v_axis = normal.cross(&u_axis);
+    let mut flat: Vec<(f64, f64, Vector4)> = points.iter().map(|point| {
+        let offset = point - &center;
+        return (offset.dot(&u_axis), offset.dot(&v_axis), *point);
+    }).collect();
+    flat.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+    //Andrew's monotone chain, which walks along the sorted points once each way, keeping only those where the outline
+    //turns counter-clockwise. The first walk finds the lower half of the outline and the second the upper half.
+    let turns_left = |a: &(f64, f64, Vector4), b: &(f64, f64, Vector4), c: &(f64, f64, Vector4)| {
+        let (to_b, to_c) = ((b.0 - a.0, b.1 - a.1), (c.0 - b.0, c.1 - b.1));
+        let length = (to_b.0 * to_b.0 + to_b.1 * to_b.1).max(to_c.0 * to_c.0 + to_c.1 * to_c.1).sqrt();
+        return to_b.0 * to_c.1 - to_b.1 * to_c.0 > epsilon * length;
+    };
+    let mut corners = Vec::<(f64, f64, Vector4)>::with_capacity(flat.len());
+    for walk in 0..2{
+        let start = corners.len();
+        let walk_points: Vec<&(f64, f64, Vector4)> = match walk {
+            0 => flat.iter().collect(),
+            _ => flat.iter().rev().collect(),
+        };
+        for point in walk_points{
+            while corners.len() >= start + 2 && !turns_left(&corners[corners.len() - 2], &corners[corners.len() - 1], point) {
+                corners.pop();
+            }
+            corners.push(*point);
+        }
+        //The last point of each walk is the first of the next.
+        corners.pop();
+    }
+    return corners.into_iter().map(|corner| corner.2).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube_points() -> Vec<Vector4>{
+        let mut points = Vec::<Vector4>::new();
+        for i in 0..8{
+            points.push(Vector4::point((i & 1) as f64, ((i >> 1) & 1) as f64, ((i >> 2) & 1) as f64));
+        }
+        return points;
+    }
+    //Checks that every face winds counter-clockwise seen from outside, with the centroid of all the points behind it.
+    fn assert_outward(faces: &[Vec<Vector4>], inside: &Vector4){
+        for face in faces.iter(){
+            assert!(face.len() >= 3);
+            let first = &face[0];
+            for i in 1..(face.len() - 1){
+                let normal = (&face[i] - first).cross(&(&face[i + 1] - first));
+                assert!(normal.len() > 0.0);
+                assert!(normal.dot(&(first - inside)) > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn cube_has_six_square_faces(){
+        let faces = hull_faces(&cube_points()).unwrap();
+        assert_eq!(faces.len(), 6);
+        assert!(faces.iter().all(|face| face.len() == 4));
+        assert_outward(&faces, &Vector4::point(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn face_and_interior_points_are_left_out(){
+        //The center of the top face is listed first, then the corners, the middle of an edge and the center of the cube.
+        let mut points = vec![Vector4::point(0.5, 1.0, 0.5)];
+        points.extend(cube_points());
+        points.push(Vector4::point(0.5, 0.0, 0.0));
+        points.push(Vector4::point(0.5, 0.5, 0.5));
+        let faces = hull_faces(&points).unwrap();
+        assert_eq!(faces.len(), 6);
+        assert!(faces.iter().all(|face| face.len() == 4));
+        assert_outward(&faces, &Vector4::point(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn tetrahedron_has_four_triangles(){
+        let points = [Vector4::point(0.0, 0.0, 0.0), Vector4::point(1.0, 0.0, 0.0), Vector4::point(0.0, 1.0, 0.0), Vector4::point(0.0, 0.0, 1.0)];
+        let faces = hull_faces(&points).unwrap();
+        assert_eq!(faces.len(), 4);
+        assert!(faces.iter().all(|face| face.len() == 3));
+        assert_outward(&faces, &Vector4::point(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn flat_points_have_no_hull(){
+        let points = [Vector4::point(0.0, 0.0, 0.0), Vector4::point(1.0, 0.0, 0.0), Vector4::point(0.0, 1.0, 0.0), Vector4::point(1.0, 1.0, 0.0)];
+        assert!(hull_faces(&points).is_none());
+        assert!(hull_faces(&points[..3]).is_none());
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+use crate::matrix::Vector4;
+use crate::shape::{LightingProps,Shape,Interval,Hit};
+use crate::bounding_box::BoundingBox;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum CsgOperation{
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOperation{
+    pub fn from_token(token: &str) -> Option<Self>{
+        return match token {
+            "UNION" => Some(Self::Union),
+            "INTERSECTION" => Some(Self::Intersection),
+            "DIFFERENCE" => Some(Self::Difference),
+            &_ => None,
+        };
+    }
+    fn inside(&self, in_a: bool, in_b: bool) -> bool{
+        return match self {
+            Self::Union => in_a || in_b,
+            Self::Intersection => in_a && in_b,
+            Self::Difference => in_a && !in_b,
+        };
+    }
+}
+
+impl fmt::Display for CsgOperation{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Self::Union => write!(f, "Union"),
+            Self::Intersection => write!(f, "Intersection"),
+            Self::Difference => write!(f, "Difference"),
+        };
+    }
+}
+
+//Combines the intervals of two solids into the intervals inside the result of the operation.
+//Both lists must be in order of t.
+pub fn combine_intervals(a: &[Interval], b: &[Interval], operation: CsgOperation) -> Vec<Interval>{
+    //Each event is the t and outward normal of a boundary, whether it belongs to a and whether it is an entry.
+    let mut events = Vec::<(f64, Vector4, bool, bool)>::with_capacity(2 * (a.len() + b.len()));
+    for (intervals, from_a) in [(a, true), (b, false)]{
+        for interval in intervals.iter(){
+            events.push((interval.enter.0, interval.enter.1, from_a, true));
+            events.push((interval.exit.0, interval.exit.1, from_a, false));
+        }
+    }
+    events.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    let mut result = Vec::<Interval>::new();
+    let (mut in_a, mut in_b) = (false, false);
+    let mut enter: Option<(f64, Vector4)> = None;
+    for (t, mut normal, from_a, entering) in events.into_iter(){
+        let was_inside = operation.inside(in_a, in_b);
+        if from_a {
+            in_a = entering;
+        }
+        else {
+            in_b = entering;
+        }
+        let is_inside = operation.inside(in_a, in_b);
+        if was_inside == is_inside {
+            continue;
+        }
+
+        //The surface of a subtracted shape faces into the result.
+        if operation == CsgOperation::Difference && !from_a {
+            normal *= -1.0;
+        }
+        match enter {
+            None => enter = Some((t, normal)),
+            Some(enter_boundary) => {
+                result.push(Interval{enter: enter_boundary, exit: (t, normal)});
+                enter = None;
+            },
+        }
+    }
+    return result;
+}
+
+//A solid made by combining solid shapes with a union, intersection or difference.
+//With more than two shapes, the operation is applied from left to right, so a difference subtracts
+//every other shape from the first one.
+pub struct Csg{
+    pub operation: CsgOperation,
+    pub shapes: Vec<Box<dyn Shape>>,
+    pub lighting_props: LightingProps,
+}
+
+impl Csg{
+    pub fn new(operation: CsgOperation, shapes: Vec<Box<dyn Shape>>, lighting_props: LightingProps) -> Self{
+        return Self{operation, shapes, lighting_props};
+    }
+}
+
+impl Shape for Csg{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        let mut bounds = self.shapes[0].bounding_box();
+        for shape in self.shapes[1..].iter(){
+            match self.operation {
+                CsgOperation::Union => bounds.grow(&shape.bounding_box()),
+                CsgOperation::Intersection => bounds = bounds.intersection(&shape.bounding_box()),
+                CsgOperation::Difference => {},
+            }
+        }
+        return bounds;
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        let mut intervals = self.shapes[0].check_intervals(origin, ray);
+        for shape in self.shapes[1..].iter(){
+            if intervals.is_empty() && self.operation != CsgOperation::Union {
+                break;
+            }
+            intervals = combine_intervals(&intervals, &shape.check_intervals(origin, ray), self.operation);
+        }
+        return intervals;
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        for interval in self.check_intervals(origin, ray).iter(){
+            for (t, normal) in [interval.enter, interval.exit]{
+                if t >= max {
+                    return None;
+                }
+                if t > min {
+                    let mut normal = normal;
+                    let front_face = normal.dot(ray) <= 0.0;
+                    if !front_face {
+                        normal *= -1.0;
+                    }
+                    let mut col_pt = ray.clone();
+                    col_pt *= t;
+                    col_pt += origin;
+                    col_pt.force_point();
+                    let mut hit = Hit::new(t, col_pt, normal, front_face);
+                    (hit.uv, hit.tangent) = Hit::box_coords(&col_pt, &normal);
+                    return Some(hit);
+                }
+            }
+        }
+        return None;
+    }
+}
+
+impl fmt::Display for Csg{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} of {} shapes with color {}:\n", self.operation, self.shapes.len(), self.lighting_props.color)?;
+        for shape in self.shapes.iter(){
+            write!(f, "\t\t-{}\n", shape.to_string().replace("\n", "\n\t\t "))?;
+        }
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //An interval along the z axis, entered through a surface facing back along the ray and left through one facing along it.
+    fn interval(enter: f64, exit: f64) -> Interval{
+        return Interval{enter: (enter, Vector4::vec(0.0, 0.0, -1.0)), exit: (exit, Vector4::vec(0.0, 0.0, 1.0))};
+    }
+    fn spans(intervals: &[Interval]) -> Vec<(f64, f64)>{
+        return intervals.iter().map(|interval| (interval.enter.0, interval.exit.0)).collect();
+    }
+
+    #[test]
+    fn overlapping_intervals(){
+        let (a, b) = ([interval(1.0, 3.0)], [interval(2.0, 4.0)]);
+        assert_eq!(spans(&combine_intervals(&a, &b, CsgOperation::Union)), vec![(1.0, 4.0)]);
+        assert_eq!(spans(&combine_intervals(&a, &b, CsgOperation::Intersection)), vec![(2.0, 3.0)]);
+        let difference = combine_intervals(&a, &b, CsgOperation::Difference);
+        assert_eq!(spans(&difference), vec![(1.0, 2.0)]);
+        //The ray leaves the difference where it enters the subtracted shape, whose surface is turned to face out of the result.
+        assert!(difference[0].exit.1.z() > 0.0);
+    }
+
+    #[test]
+    fn separate_intervals(){
+        let (a, b) = ([interval(1.0, 2.0)], [interval(3.0, 4.0)]);
+        assert_eq!(spans(&combine_intervals(&a, &b, CsgOperation::Union)), vec![(1.0, 2.0), (3.0, 4.0)]);
+        assert!(combine_intervals(&a, &b, CsgOperation::Intersection).is_empty());
+        assert_eq!(spans(&combine_intervals(&a, &b, CsgOperation::Difference)), vec![(1.0, 2.0)]);
+    }
+
+    #[test]
+    fn nested_intervals(){
+        //b lies inside of a, so subtracting it splits a in two, while subtracting a from b leaves nothing.
+        let (a, b) = ([interval(1.0, 5.0)], [interval(2.0, 3.0)]);
+        assert_eq!(spans(&combine_intervals(&a, &b, CsgOperation::Union)), vec![(1.0, 5.0)]);
+        assert_eq!(spans(&combine_intervals(&a, &b, CsgOperation::Intersection)), vec![(2.0, 3.0)]);
+        assert_eq!(spans(&combine_intervals(&a, &b, CsgOperation::Difference)), vec![(1.0, 2.0), (3.0, 5.0)]);
+        assert!(combine_intervals(&b, &a, CsgOperation::Difference).is_empty());
+    }
+
+    #[test]
+    fn several_intervals_each(){
+        //Like a ray through two tori, each crossing its tube twice.
+        let a = [interval(1.0, 2.0), interval(5.0, 6.0)];
+        let b = [interval(1.5, 3.0), interval(4.0, 5.5)];
+        assert_eq!(spans(&combine_intervals(&a, &b, CsgOperation::Union)), vec![(1.0, 3.0), (4.0, 6.0)]);
+        assert_eq!(spans(&combine_intervals(&a, &b, CsgOperation::Intersection)), vec![(1.5, 2.0), (5.0, 5.5)]);
+        assert_eq!(spans(&combine_intervals(&a, &b, CsgOperation::Difference)), vec![(1.0, 1.5), (5.5, 6.0)]);
+        assert!(combine_intervals(&[], &b, CsgOperation::Intersection).is_empty());
+        assert_eq!(spans(&combine_intervals(&a, &[], CsgOperation::Difference)), spans(&a));
+    }
+}
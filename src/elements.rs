@@ -1,9 +1,11 @@
 use std::fmt;
 use crate::matrix::Vector4;
 //use crate::matrix::Matrix4;
-use crate::shape::{LightingProps,Shape};
+use crate::shape::{LightingProps,Shape,Interval,Hit,read_props_from_tokens,intervals_from_crossings};
 use crate::polygon::{SpatialProps,Polygon};
 use crate::mesh_shape::MeshShape;
+use crate::bounding_box::BoundingBox;
+use crate::convex_hull;
 
 pub struct Light{
     pub pos: Vector4,
@@ -42,7 +44,7 @@ pub struct Dodecahedron{
 }
 impl Dodecahedron {
     pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
-        return match MeshShape::read_from_tokens(tokens){
+        return match read_props_from_tokens(tokens){
             Some((spatial_props, lighting_props)) => {
             // The golden ratio, φ, and its reciprocal are used to define the vertices.
             const PHI: f64 = 1.618033988749894848204586834365638118_f64;
@@ -144,18 +146,39 @@ impl Shape for Dodecahedron{
     fn lighting_props(&self) -> &LightingProps{
         return &self.mesh_shape.lighting_props;
     }
-    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<(f64,Vector4,Vector4)>{
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.mesh_shape.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.mesh_shape.bounding_box();
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        return self.mesh_shape.check_intervals(origin, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
         return self.mesh_shape.check_collision(origin, ray, min, max);
     }
 }
 
+impl fmt::Display for Dodecahedron{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let spatial_props = &self.mesh_shape.spatial_props;
+        let lighting_props = &self.mesh_shape.lighting_props;
+        write!(f, "Dodecahedron with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", spatial_props.scale, spatial_props.r_x, spatial_props.r_y, spatial_props.r_z, lighting_props.color, spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", lighting_props.amb, lighting_props.diff, lighting_props.spec, lighting_props.refl, lighting_props.bright);
+    }
+}
+
 pub struct Tetrahedron{
     pub mesh_shape: MeshShape,
 }
 
 impl Tetrahedron {
     pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
-        return match MeshShape::read_from_tokens(tokens){
+        return match read_props_from_tokens(tokens){
                 Some((spatial_props, lighting_props)) => {
                 let half_height = f64::sqrt(2.0/3.0);
                 let half_triangle_height = f64::sqrt(3.0) / 2.0;
@@ -182,7 +205,19 @@ impl Shape for Tetrahedron{
     fn lighting_props(&self) -> &LightingProps{
         return &self.mesh_shape.lighting_props;
     }
-    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<(f64,Vector4,Vector4)>{
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.mesh_shape.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.mesh_shape.bounding_box();
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        return self.mesh_shape.check_intervals(origin, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
         return self.mesh_shape.check_collision(origin, ray, min, max);
     }
 }
@@ -202,7 +237,7 @@ pub struct Cube{
 
 impl Cube {
     pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
-        return match MeshShape::read_from_tokens(tokens){
+        return match read_props_from_tokens(tokens){
             Some((spatial_props, lighting_props)) => {
                 let polygons=vec![
                 Polygon::new(vec![Vector4::point( 1.0,1.0,1.0),Vector4::point(1.0,1.0,-1.0),Vector4::point(1.0,-1.0,-1.0),Vector4::point( 1.0,-1.0,1.0)], &spatial_props),
@@ -223,7 +258,19 @@ impl Shape for Cube{
     fn lighting_props(&self) -> &LightingProps{
         return &self.mesh_shape.lighting_props;
     }
-    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<(f64,Vector4,Vector4)>{
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.mesh_shape.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.mesh_shape.bounding_box();
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        return self.mesh_shape.check_intervals(origin, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
         return self.mesh_shape.check_collision(origin, ray, min, max);
     }
 }
@@ -236,62 +283,290 @@ impl fmt::Display for Cube{
     }
 }
 
-pub struct Sphere{
-    pub spatial_props: SpatialProps,
-    pub lighting_props: LightingProps,
+pub struct Octahedron{
+    pub mesh_shape: MeshShape,
 }
-impl Sphere{
+
+impl Octahedron {
     pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
-        if tokens.len() < 16 || tokens.len() > 19 {
-            return None
+        return match read_props_from_tokens(tokens){
+            Some((spatial_props, lighting_props)) => {
+                let polygons = Self::triangles().into_iter().map(|triangle| Polygon::new(triangle.to_vec(), &spatial_props)).collect();
+                let mesh_shape = MeshShape::new(spatial_props, lighting_props, polygons);
+                Some(Self{mesh_shape})
+            },
+            None => None,
+        }
+    }
+    //The faces of the octahedron with its corners on the axes at a distance of 1.0, one face for each octant.
+    fn triangles() -> Vec<[Vector4;3]>{
+        let mut triangles = Vec::<[Vector4;3]>::with_capacity(8);
+        for octant in 0..8{
+            let x = if octant & 1 == 0 { 1.0 } else { -1.0 };
+            let y = if octant & 2 == 0 { 1.0 } else { -1.0 };
+            let z = if octant & 4 == 0 { 1.0 } else { -1.0 };
+            triangles.push([Vector4::point(x, 0.0, 0.0), Vector4::point(0.0, y, 0.0), Vector4::point(0.0, 0.0, z)]);
         }
+        return triangles;
+    }
+}
+impl Shape for Octahedron{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.mesh_shape.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.mesh_shape.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.mesh_shape.bounding_box();
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        return self.mesh_shape.check_intervals(origin, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        return self.mesh_shape.check_collision(origin, ray, min, max);
+    }
+}
+impl fmt::Display for Octahedron{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let spatial_props = &self.mesh_shape.spatial_props;
+        let lighting_props = &self.mesh_shape.lighting_props;
+        write!(f, "Octahedron with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", spatial_props.scale, spatial_props.r_x, spatial_props.r_y, spatial_props.r_z, lighting_props.color, spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", lighting_props.amb, lighting_props.diff, lighting_props.spec, lighting_props.refl, lighting_props.bright);
+    }
+}
 
-        //Parse the tokens into f64s...
-        let token_slice: &[&str] = &tokens[2..];
-        let mut parsed_tokens: [f64;17] = [0.0;17];
-        for i in 0..token_slice.len(){
-            let parse_result = token_slice[i].to_string().trim().parse::<f64>();
-            match parse_result{
-                Err(_e) => return None,
-                Ok(num) => parsed_tokens[i] = num,
+pub struct Icosahedron{
+    pub mesh_shape: MeshShape,
+}
+
+impl Icosahedron {
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        return match read_props_from_tokens(tokens){
+            Some((spatial_props, lighting_props)) => {
+                let polygons = Self::triangles().into_iter().map(|triangle| Polygon::new(triangle.to_vec(), &spatial_props)).collect();
+                let mesh_shape = MeshShape::new(spatial_props, lighting_props, polygons);
+                Some(Self{mesh_shape})
+            },
+            None => None,
+        }
+    }
+    //The faces of the icosahedron with its corners at a distance of 1.0 from the center.
+    fn triangles() -> Vec<[Vector4;3]>{
+        //The corners lie on three golden rectangles in the XY, YZ and ZX planes.
+        let phi = (1.0 + f64::sqrt(5.0)) / 2.0;
+        let mut corners = Vec::<Vector4>::with_capacity(12);
+        for a in [-1.0, 1.0]{
+            for b in [-phi, phi]{
+                corners.push(Vector4::point(a, b, 0.0));
+                corners.push(Vector4::point(0.0, a, b));
+                corners.push(Vector4::point(b, 0.0, a));
             }
         }
 
-        let pos = Vector4::point_from_slice(&parsed_tokens[0..3]);
-        let scale = Vector4::vec_from_slice(&parsed_tokens[3..6]);
-        let mut r_x: f64 = 0.0;
-        let mut r_y: f64 = 0.0;
-        let mut r_z: f64 = 0.0;
-        let mut color_start: usize = 6;
-        let mut lighting_param_start: usize = 9;
+        //Every set of three corners that are all an edge length apart forms a face.
+        let edge_len_sq = 4.0;
+        let is_edge = |a: &Vector4, b: &Vector4| {
+            let mut edge = a.clone();
+            edge -= b;
+            return (edge.len_sq() - edge_len_sq).abs() < 0.001;
+        };
+        let scale = 1.0 / f64::sqrt(1.0 + phi * phi);
+        let mut triangles = Vec::<[Vector4;3]>::with_capacity(20);
+        for i in 0..corners.len(){
+            for j in (i + 1)..corners.len(){
+                for k in (j + 1)..corners.len(){
+                    if is_edge(&corners[i], &corners[j]) && is_edge(&corners[j], &corners[k]) && is_edge(&corners[k], &corners[i]) {
+                        let mut triangle = [corners[i], corners[j], corners[k]];
+                        for corner in triangle.iter_mut(){
+                            *corner *= scale;
+                            corner.force_point();
+                        }
+                        triangles.push(triangle);
+                    }
+                }
+            }
+        }
+        return triangles;
+    }
+}
+impl Shape for Icosahedron{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.mesh_shape.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.mesh_shape.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.mesh_shape.bounding_box();
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        return self.mesh_shape.check_intervals(origin, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        return self.mesh_shape.check_collision(origin, ray, min, max);
+    }
+}
+impl fmt::Display for Icosahedron{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let spatial_props = &self.mesh_shape.spatial_props;
+        let lighting_props = &self.mesh_shape.lighting_props;
+        write!(f, "Icosahedron with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", spatial_props.scale, spatial_props.r_x, spatial_props.r_y, spatial_props.r_z, lighting_props.color, spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", lighting_props.amb, lighting_props.diff, lighting_props.spec, lighting_props.refl, lighting_props.bright);
+    }
+}
+
+//Geodesic spheres with more subdivisions than this would have hundreds of thousands of faces.
+const MAX_GEODESIC_SUBDIVISIONS : u32 = 7;
+
+//An approximation of the unit sphere made by repeatedly splitting each face of an icosahedron into four
+//and pushing the new corners out onto the sphere.
+pub struct GeodesicSphere{
+    pub mesh_shape: MeshShape,
+    pub subdivisions: u32,
+}
+
+impl GeodesicSphere {
+    //Reads a GEODESIC line, which has the number of subdivisions after the name and is otherwise laid out like a sphere.
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        if tokens.len() < 3 {
+            return None;
+        }
+        let subdivisions = tokens[2].to_string().trim().parse::<u32>().ok()?;
+        if subdivisions > MAX_GEODESIC_SUBDIVISIONS {
+            return None;
+        }
+
+        let mut shape_tokens = tokens.clone();
+        shape_tokens.remove(2);
+        let (spatial_props, lighting_props) = read_props_from_tokens(&shape_tokens)?;
+        let mut triangles = Icosahedron::triangles();
+        for _i in 0..subdivisions{
+            triangles = triangles.iter().flat_map(Self::subdivide).collect();
+        }
+        let polygons = triangles.into_iter().map(|triangle| Polygon::new(triangle.to_vec(), &spatial_props)).collect();
+        let mesh_shape = MeshShape::new(spatial_props, lighting_props, polygons);
+        return Some(Self{mesh_shape, subdivisions});
+    }
+    //Splits a triangle on the unit sphere into four, with the midpoints of its edges moved onto the sphere.
+    fn subdivide(triangle: &[Vector4;3]) -> [[Vector4;3];4]{
+        let midpoint = |a: &Vector4, b: &Vector4| {
+            let mut midpoint = a + b;
+            midpoint.force_vec();
+            midpoint.normalize();
+            midpoint.force_point();
+            return midpoint;
+        };
+        let [a, b, c] = triangle;
+        let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+        return [[*a, ab, ca], [ab, *b, bc], [ca, bc, *c], [ab, bc, ca]];
+    }
+}
+impl Shape for GeodesicSphere{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.mesh_shape.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.mesh_shape.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.mesh_shape.bounding_box();
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        return self.mesh_shape.check_intervals(origin, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        return self.mesh_shape.check_collision(origin, ray, min, max);
+    }
+}
+impl fmt::Display for GeodesicSphere{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let spatial_props = &self.mesh_shape.spatial_props;
+        let lighting_props = &self.mesh_shape.lighting_props;
+        write!(f, "Geodesic sphere of {} subdivisions with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.subdivisions, spatial_props.scale, spatial_props.r_x, spatial_props.r_y, spatial_props.r_z, lighting_props.color, spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", lighting_props.amb, lighting_props.diff, lighting_props.spec, lighting_props.refl, lighting_props.bright);
+    }
+}
+
+//The convex hull of a list of points given in the scene file, for solids such as gems and crystals.
+pub struct ConvexPolyhedron{
+    pub mesh_shape: MeshShape,
+}
 
-        if tokens.len() > 16 {
-            r_x = parsed_tokens[6];
-            color_start += 1;
-            lighting_param_start += 1;
+impl ConvexPolyhedron {
+    //Reads a CONVEX line, which has the number of points and their coordinates after the name and is otherwise laid out like a sphere.
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        if tokens.len() < 3 {
+            return None;
         }
-        if tokens.len() > 17 {
-            r_y = parsed_tokens[7];
-            color_start += 1;
-            lighting_param_start += 1;
+        let point_count = tokens[2].to_string().trim().parse::<usize>().ok()?;
+        let points_end = 3 + 3 * point_count;
+        if tokens.len() < points_end {
+            return None;
         }
-        if tokens.len() > 18 {
-            r_z = parsed_tokens[8];
-            color_start += 1;
-            lighting_param_start += 1;
+        let mut points = Vec::<Vector4>::with_capacity(point_count);
+        for coords in tokens[3..points_end].chunks(3){
+            let values = coords.iter().map(|token| token.to_string().trim().parse::<f64>().ok()).collect::<Option<Vec<f64>>>()?;
+            points.push(Vector4::point_from_slice(&values));
         }
-        let spatial_props = SpatialProps::new(pos, scale, r_x, r_y, r_z);
 
-        let lighting_values = &parsed_tokens[lighting_param_start..];
-        let color = Vector4::vec_from_slice(&parsed_tokens[color_start..lighting_param_start]);
-        let amb = lighting_values[0];
-        let diff = lighting_values[1];
-        let spec = lighting_values[2];
-        let refl = lighting_values[3];
-        let bright = lighting_values[4];
+        let mut shape_tokens = tokens.clone();
+        shape_tokens.drain(2..points_end);
+        let (spatial_props, lighting_props) = read_props_from_tokens(&shape_tokens)?;
+        let faces = convex_hull::hull_faces(&points)?;
+        let polygons = faces.into_iter().map(|face| Polygon::from_winding(face, &spatial_props)).collect();
+        let mesh_shape = MeshShape::new(spatial_props, lighting_props, polygons);
+        return Some(Self{mesh_shape});
+    }
+}
+impl Shape for ConvexPolyhedron{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.mesh_shape.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.mesh_shape.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.mesh_shape.bounding_box();
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        return self.mesh_shape.check_intervals(origin, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        return self.mesh_shape.check_collision(origin, ray, min, max);
+    }
+}
+impl fmt::Display for ConvexPolyhedron{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let spatial_props = &self.mesh_shape.spatial_props;
+        let lighting_props = &self.mesh_shape.lighting_props;
+        write!(f, "Convex polyhedron of {} faces with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.mesh_shape.polygons.len(), spatial_props.scale, spatial_props.r_x, spatial_props.r_y, spatial_props.r_z, lighting_props.color, spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", lighting_props.amb, lighting_props.diff, lighting_props.spec, lighting_props.refl, lighting_props.bright);
+    }
+}
 
-        let lighting_props = LightingProps::new(color, amb, diff, spec, refl, bright);
-        return Some(Self{spatial_props, lighting_props});
+pub struct Sphere{
+    pub spatial_props: SpatialProps,
+    pub lighting_props: LightingProps,
+}
+impl Sphere{
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        return match read_props_from_tokens(tokens){
+            Some((spatial_props, lighting_props)) => Some(Self{spatial_props, lighting_props}),
+            None => None,
+        }
     }
 }
 
@@ -299,7 +574,37 @@ impl Shape for Sphere{
     fn lighting_props(&self) -> &LightingProps{
         return &self.lighting_props;
     }
-    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<(f64,Vector4,Vector4)>{
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.spatial_props.bounding_box(&BoundingBox::unit());
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+
+        let a = ray_prime.dot(&ray_prime);
+        let b = origin_prime.dot(&ray_prime);
+        let c = origin_prime.dot(&origin_prime) - 1.0;
+        let det = b * b - a * c;
+        if det <= 0.0 {
+            return Vec::new();
+        }
+        let sqrt_det = det.sqrt();
+        let crossings = [(-b - sqrt_det) / a, (-b + sqrt_det) / a].map(|t| {
+            let mut normal_prime = ray_prime.clone();
+            normal_prime *= t;
+            normal_prime += &origin_prime;
+            normal_prime.force_vec();
+            return (t, normal_prime.apply_inv_transpose(&self.spatial_props.inv_transp));
+        });
+        return vec![Interval{enter: crossings[0], exit: crossings[1]}];
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
         let origin_prime = &self.spatial_props.inv_matrix * origin;
         let ray_prime = &self.spatial_props.inv_matrix * ray;
         
@@ -329,6 +634,10 @@ impl Shape for Sphere{
                 let mut col_pt_prime = ray_prime.clone();
                 col_pt_prime *= t;
                 col_pt_prime += &origin_prime;
+                let front_face = col_pt_prime.dot(&ray_prime) < 0.0;
+                //The longitude and latitude of the point, with u running around the y axis and v from the bottom to the top.
+                let uv = (0.5 + col_pt_prime.x().atan2(col_pt_prime.z()) / (2.0 * std::f64::consts::PI), 0.5 + col_pt_prime.y().clamp(-1.0, 1.0).asin() / std::f64::consts::PI);
+                let tangent_prime = Vector4::vec(col_pt_prime.z(), 0.0, -col_pt_prime.x());
     
                 if a > origin_len_sq {
                     col_pt_prime *= -1.0;
@@ -337,7 +646,9 @@ impl Shape for Sphere{
                 let mut normal =  &self.spatial_props.inv_transp * &col_pt_prime;
                 normal.force_vec();
 
-                return Some((t, col_pt, normal));
+                let mut hit = Hit::new(t, col_pt, normal, front_face);
+                hit.set_uv(uv, &tangent_prime, &self.spatial_props);
+                return Some(hit);
             }
         }
         return None;
@@ -351,3 +662,492 @@ impl fmt::Display for Sphere{
     }
 }
 
+
+//Finds where a ray crosses the y = 0 plane in the object space of a flat shape, keeping the collision
+//only if inside(x, z) holds. The normal returned faces the side the ray came from, so both sides are lit.
+//The texture coordinates map x and z from -1.0 to 1.0 onto 0.0 to 1.0.
+fn check_flat_collision(spatial_props: &SpatialProps, origin: &Vector4, ray: &Vector4, min: f64, max: f64, inside: impl Fn(f64, f64) -> bool) -> Option<Hit>{
+    let origin_prime = &spatial_props.inv_matrix * origin;
+    let ray_prime = &spatial_props.inv_matrix * ray;
+
+    let t = -origin_prime.y() / ray_prime.y();
+    //Written so that the NaN produced by a ray lying in the plane is rejected.
+    if !(t > min && t < max) {
+        return None;
+    }
+    let x = origin_prime.x() + t * ray_prime.x();
+    let z = origin_prime.z() + t * ray_prime.z();
+    if !inside(x, z) {
+        return None;
+    }
+
+    let mut col_pt = ray.clone();
+    col_pt *= t;
+    col_pt += origin;
+    col_pt.force_point();
+
+    let normal_prime = match ray_prime.y() > 0.0 {
+        true => Vector4::vec(0.0, -1.0, 0.0),
+        false => Vector4::vec(0.0, 1.0, 0.0),
+    };
+    let normal = normal_prime.apply_inv_transpose(&spatial_props.inv_transp);
+    let mut hit = Hit::new(t, col_pt, normal, ray_prime.y() <= 0.0);
+    hit.set_uv((0.5 * (x + 1.0), 0.5 * (z + 1.0)), &Vector4::vec(1.0, 0.0, 0.0), spatial_props);
+    return Some(hit);
+}
+
+//An infinite plane, given either by a point on it and its normal or as the y = 0 plane placed with spatial properties.
+pub struct Plane{
+    pub point: Vector4,
+    pub normal: Vector4,
+    //Places the plane y = 0 of object space onto the plane, which is the space that patterns on the plane are worked out in.
+    pub spatial_props: SpatialProps,
+    pub lighting_props: LightingProps,
+}
+impl Plane{
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        if tokens.len() > 2 && tokens[2] == "NORMAL" {
+            //PLANE name NORMAL x y z nX nY nZ r g b kA kD kS kR n
+            if tokens.len() != 17 {
+                return None;
+            }
+            let mut parsed_tokens: [f64;14] = [0.0;14];
+            for i in 0..14{
+                match tokens[i + 3].to_string().trim().parse::<f64>(){
+                    Err(_e) => return None,
+                    Ok(num) => parsed_tokens[i] = num,
+                }
+            }
+            let point = Vector4::point_from_slice(&parsed_tokens[0..3]);
+            let normal = Vector4::vec_from_slice(&parsed_tokens[3..6]);
+            if normal.len_sq() <= 0.0 {
+                return None;
+            }
+            //Tips the y axis over towards z about the x axis and then turns it about the z axis until it lies along the normal.
+            let mut unit_normal = normal.clone();
+            unit_normal.normalize();
+            let r_x = unit_normal.z().clamp(-1.0, 1.0).asin();
+            let r_z = (-unit_normal.x()).atan2(unit_normal.y());
+            let spatial_props = SpatialProps::new(point, Vector4::vec(1.0, 1.0, 1.0), r_x, 0.0, r_z);
+            let lighting_props = LightingProps::from_slice(&parsed_tokens[6..]);
+            return Some(Self{point, normal, spatial_props, lighting_props});
+        }
+        return match read_props_from_tokens(tokens){
+            Some((spatial_props, lighting_props)) => {
+                let point = &spatial_props.matrix * &Vector4::point(0.0, 0.0, 0.0);
+                let normal = Vector4::vec(0.0, 1.0, 0.0).apply_inv_transpose(&spatial_props.inv_transp);
+                Some(Self{point, normal, spatial_props, lighting_props})
+            },
+            None => None,
+        }
+    }
+}
+impl Shape for Plane{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return BoundingBox::infinite();
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        let ray_proj = ray.dot(&self.normal);
+        let mut to_plane = self.point.clone();
+        to_plane -= origin;
+        let t = to_plane.dot(&self.normal) / ray_proj;
+        if !(t > min && t < max) {
+            return None;
+        }
+
+        let mut col_pt = ray.clone();
+        col_pt *= t;
+        col_pt += origin;
+        col_pt.force_point();
+
+        let mut normal = self.normal.clone();
+        if ray_proj > 0.0 {
+            normal *= -1.0;
+        }
+        //The texture coordinates are distances along two directions in the plane from its point.
+        let mut hit = Hit::new(t, col_pt, normal, ray_proj <= 0.0);
+        let mut u_axis = Hit::any_tangent(&self.normal);
+        u_axis.normalize();
+        let mut v_axis = self.normal.cross(&u_axis);
+        v_axis.normalize();
+        let mut offset = col_pt.clone();
+        offset -= &self.point;
+        hit.uv = (offset.dot(&u_axis), offset.dot(&v_axis));
+        hit.tangent = u_axis;
+        return Some(hit);
+    }
+}
+impl fmt::Display for Plane{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Plane with normal {} and color {} through {}.\n", self.normal, self.lighting_props.color, self.point)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
+
+//A square from (-1,0,-1) to (1,0,1) in object space.
+pub struct Quad{
+    pub spatial_props: SpatialProps,
+    pub lighting_props: LightingProps,
+}
+impl Quad{
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        return match read_props_from_tokens(tokens){
+            Some((spatial_props, lighting_props)) => Some(Self{spatial_props, lighting_props}),
+            None => None,
+        }
+    }
+}
+impl Shape for Quad{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        let flat_box = BoundingBox::new(Vector4::point(-1.0, 0.0, -1.0), Vector4::point(1.0, 0.0, 1.0));
+        return self.spatial_props.bounding_box(&flat_box);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        return check_flat_collision(&self.spatial_props, origin, ray, min, max, |x, z| x.abs() <= 1.0 && z.abs() <= 1.0);
+    }
+}
+impl fmt::Display for Quad{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Quad with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.spatial_props.scale, self.spatial_props.r_x, self.spatial_props.r_y, self.spatial_props.r_z, self.lighting_props.color, self.spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
+
+//A disk of radius 1 centered on the origin in the y = 0 plane of object space.
+pub struct Disk{
+    pub spatial_props: SpatialProps,
+    pub lighting_props: LightingProps,
+}
+impl Disk{
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        return match read_props_from_tokens(tokens){
+            Some((spatial_props, lighting_props)) => Some(Self{spatial_props, lighting_props}),
+            None => None,
+        }
+    }
+}
+impl Shape for Disk{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        let flat_box = BoundingBox::new(Vector4::point(-1.0, 0.0, -1.0), Vector4::point(1.0, 0.0, 1.0));
+        return self.spatial_props.bounding_box(&flat_box);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        return check_flat_collision(&self.spatial_props, origin, ray, min, max, |x, z| x * x + z * z <= 1.0);
+    }
+}
+impl fmt::Display for Disk{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Disk with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.spatial_props.scale, self.spatial_props.r_x, self.spatial_props.r_y, self.spatial_props.r_z, self.lighting_props.color, self.spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
+
+//A flat polygon in the y = 0 plane of object space, which may be concave and may have holes.
+pub struct FlatPolygon{
+    pub spatial_props: SpatialProps,
+    pub lighting_props: LightingProps,
+    pub polygon: Polygon,
+}
+impl FlatPolygon{
+    //Reads a POLYGON line, which has the number of corners and their x and z coordinates after the name.
+    //Each hole follows as HOLE, its number of corners and their coordinates. The rest is laid out like a sphere.
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        let (mut outlines, next) = Self::read_outlines(tokens, 2)?;
+
+        let mut shape_tokens = tokens.clone();
+        shape_tokens.drain(2..next);
+        let (spatial_props, lighting_props) = read_props_from_tokens(&shape_tokens)?;
+        let points = outlines.remove(0);
+        if Polygon::winding_normal(&points).len_sq() <= 0.0 {
+            return None;
+        }
+        let polygon = Polygon::with_holes(points, outlines, &spatial_props);
+        return Some(Self{spatial_props, lighting_props, polygon});
+    }
+    //Reads an outline and its holes starting at the given token, as points in the y = 0 plane.
+    //Returns the outline followed by the holes, and the index of the first token after them.
+    pub fn read_outlines(tokens: &Vec<&str>, start: usize) -> Option<(Vec<Vec<Vector4>>, usize)>{
+        let mut outlines = Vec::<Vec<Vector4>>::new();
+        let mut next = start;
+        loop {
+            if !outlines.is_empty() {
+                if tokens.get(next) != Some(&"HOLE") {
+                    break;
+                }
+                next += 1;
+            }
+            let corner_count = tokens.get(next)?.to_string().trim().parse::<usize>().ok()?;
+            let corners_end = next + 1 + 2 * corner_count;
+            if corner_count < 3 || tokens.len() < corners_end {
+                return None;
+            }
+            let mut corners = Vec::<Vector4>::with_capacity(corner_count);
+            for coords in tokens[(next + 1)..corners_end].chunks(2){
+                let x = coords[0].to_string().trim().parse::<f64>().ok()?;
+                let z = coords[1].to_string().trim().parse::<f64>().ok()?;
+                corners.push(Vector4::point(x, 0.0, z));
+            }
+            outlines.push(corners);
+            next = corners_end;
+        }
+        return Some((outlines, next));
+    }
+}
+impl Shape for FlatPolygon{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.spatial_props.bounding_box(&self.polygon.bounding_box());
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        return check_flat_collision(&self.spatial_props, origin, ray, min, max, |x, z| self.polygon.contains(&Vector4::point(x, 0.0, z)));
+    }
+}
+impl fmt::Display for FlatPolygon{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Polygon of {} corners and {} holes with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.polygon.corner_count(), self.polygon.hole_count(), self.spatial_props.scale, self.spatial_props.r_x, self.spatial_props.r_y, self.spatial_props.r_z, self.lighting_props.color, self.spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
+
+//Reads a CYLINDER or CONE line, which has OPEN or CAPPED after the name and is otherwise laid out like a sphere.
+fn read_capped_from_tokens(tokens: &Vec<&str>) -> Option<(SpatialProps, LightingProps, bool)>{
+    if tokens.len() < 3 {
+        return None;
+    }
+    let capped = match tokens[2] {
+        "CAPPED" => true,
+        "OPEN" => false,
+        &_ => return None,
+    };
+    let mut shape_tokens = tokens.clone();
+    shape_tokens.remove(2);
+    return match read_props_from_tokens(&shape_tokens){
+        Some((spatial_props, lighting_props)) => Some((spatial_props, lighting_props, capped)),
+        None => None,
+    }
+}
+
+//Adds the collisions of a ray with the y = height plane of object space that are within the given radius of the y axis.
+fn add_cap_collisions(crossings: &mut Vec<(f64, Vector4)>, origin_prime: &Vector4, ray_prime: &Vector4, height: f64, radius: f64){
+    let t = (height - origin_prime.y()) / ray_prime.y();
+    if !t.is_finite() {
+        return;
+    }
+    let x = origin_prime.x() + t * ray_prime.x();
+    let z = origin_prime.z() + t * ray_prime.z();
+    if x * x + z * z <= radius * radius {
+        crossings.push((t, Vector4::vec(0.0, height.signum(), 0.0)));
+    }
+}
+
+//Adds the collisions with the quadratic a*t^2 + 2*b*t + c = 0 whose y values lie between -1 and 1.
+//The object space normal at a collision point is given by normal_at.
+fn add_side_collisions(crossings: &mut Vec<(f64, Vector4)>, origin_prime: &Vector4, ray_prime: &Vector4, a: f64, b: f64, c: f64, normal_at: fn(&Vector4) -> Vector4){
+    let roots = match a == 0.0 {
+        true => match b == 0.0 {
+            true => return,
+            false => [-c / (2.0 * b), f64::NAN],
+        },
+        false => {
+            let det = b * b - a * c;
+            if det < 0.0 {
+                return;
+            }
+            let sqrt_det = det.sqrt();
+            [(-b - sqrt_det) / a, (-b + sqrt_det) / a]
+        },
+    };
+    for t in roots{
+        if !t.is_finite() {
+            continue;
+        }
+        let mut col_pt_prime = ray_prime.clone();
+        col_pt_prime *= t;
+        col_pt_prime += origin_prime;
+        if col_pt_prime.y().abs() <= 1.0 {
+            crossings.push((t, normal_at(&col_pt_prime)));
+        }
+    }
+}
+
+//Picks the closest of the crossings between min and max, returning the world space collision point and normal.
+//The normal is turned to face the ray, so the inside of an open shape is lit as well as the outside.
+//The texture coordinates wrap u around the y axis and run v from the bottom of the shape to the top.
+fn closest_crossing(crossings: &[(f64, Vector4)], spatial_props: &SpatialProps, origin: &Vector4, ray: &Vector4, ray_prime: &Vector4, min: f64, max: f64) -> Option<Hit>{
+    let mut closest: Option<(f64, Vector4)> = None;
+    for (t, normal_prime) in crossings.iter(){
+        if *t > min && *t < max && closest.is_none_or(|(closest_t, _)| *t < closest_t) {
+            closest = Some((*t, *normal_prime));
+        }
+    }
+    let (t, mut normal_prime) = closest?;
+    let front_face = normal_prime.dot(ray_prime) <= 0.0;
+    if !front_face {
+        normal_prime *= -1.0;
+    }
+
+    let mut col_pt = ray.clone();
+    col_pt *= t;
+    col_pt += origin;
+    col_pt.force_point();
+    let normal = normal_prime.apply_inv_transpose(&spatial_props.inv_transp);
+    let col_pt_prime = &spatial_props.inv_matrix * &col_pt;
+    let mut hit = Hit::new(t, col_pt, normal, front_face);
+    let uv = (0.5 + col_pt_prime.x().atan2(col_pt_prime.z()) / (2.0 * std::f64::consts::PI), 0.5 * (col_pt_prime.y() + 1.0));
+    hit.set_uv(uv, &Vector4::vec(col_pt_prime.z(), 0.0, -col_pt_prime.x()), spatial_props);
+    return Some(hit);
+}
+
+//Turns crossings with object space normals into intervals with world space normals.
+fn crossing_intervals(crossings: Vec<(f64, Vector4)>, spatial_props: &SpatialProps, ray: &Vector4) -> Vec<Interval>{
+    let world_crossings = crossings.into_iter().map(|(t, normal_prime)| (t, normal_prime.apply_inv_transpose(&spatial_props.inv_transp))).collect();
+    return intervals_from_crossings(world_crossings, ray);
+}
+
+//A cylinder of radius 1 around the y axis of object space, running from y = -1 to y = 1.
+pub struct Cylinder{
+    pub spatial_props: SpatialProps,
+    pub lighting_props: LightingProps,
+    pub capped: bool,
+}
+impl Cylinder{
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        return match read_capped_from_tokens(tokens){
+            Some((spatial_props, lighting_props, capped)) => Some(Self{spatial_props, lighting_props, capped}),
+            None => None,
+        }
+    }
+    //Finds every t at which the object space line crosses the surface, with the outward object space normal there.
+    fn crossings(&self, origin_prime: &Vector4, ray_prime: &Vector4) -> Vec<(f64, Vector4)>{
+        //x^2 + z^2 = 1
+        let a = ray_prime.x() * ray_prime.x() + ray_prime.z() * ray_prime.z();
+        let b = origin_prime.x() * ray_prime.x() + origin_prime.z() * ray_prime.z();
+        let c = origin_prime.x() * origin_prime.x() + origin_prime.z() * origin_prime.z() - 1.0;
+
+        let mut crossings = Vec::<(f64, Vector4)>::with_capacity(4);
+        add_side_collisions(&mut crossings, origin_prime, ray_prime, a, b, c, |pt| Vector4::vec(pt.x(), 0.0, pt.z()));
+        if self.capped {
+            add_cap_collisions(&mut crossings, origin_prime, ray_prime, 1.0, 1.0);
+            add_cap_collisions(&mut crossings, origin_prime, ray_prime, -1.0, 1.0);
+        }
+        return crossings;
+    }
+}
+impl Shape for Cylinder{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.spatial_props.bounding_box(&BoundingBox::unit());
+    }
+    fn is_solid(&self) -> bool{
+        return self.capped;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+        return crossing_intervals(self.crossings(&origin_prime, &ray_prime), &self.spatial_props, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+        let crossings = self.crossings(&origin_prime, &ray_prime);
+        return closest_crossing(&crossings, &self.spatial_props, origin, ray, &ray_prime, min, max);
+    }
+}
+impl fmt::Display for Cylinder{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ends = if self.capped { "Capped" } else { "Open" };
+        write!(f, "{ends} cylinder with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.spatial_props.scale, self.spatial_props.r_x, self.spatial_props.r_y, self.spatial_props.r_z, self.lighting_props.color, self.spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
+
+//A cone around the y axis of object space with its apex at y = 1 and a base of radius 1 at y = -1.
+pub struct Cone{
+    pub spatial_props: SpatialProps,
+    pub lighting_props: LightingProps,
+    pub capped: bool,
+}
+impl Cone{
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        return match read_capped_from_tokens(tokens){
+            Some((spatial_props, lighting_props, capped)) => Some(Self{spatial_props, lighting_props, capped}),
+            None => None,
+        }
+    }
+    //Finds every t at which the object space line crosses the surface, with the outward object space normal there.
+    fn crossings(&self, origin_prime: &Vector4, ray_prime: &Vector4) -> Vec<(f64, Vector4)>{
+        //x^2 + z^2 = ((1 - y) / 2)^2, measuring the height h = 1 - y down from the apex.
+        let origin_h = 1.0 - origin_prime.y();
+        let ray_h = -ray_prime.y();
+        let a = ray_prime.x() * ray_prime.x() + ray_prime.z() * ray_prime.z() - 0.25 * ray_h * ray_h;
+        let b = origin_prime.x() * ray_prime.x() + origin_prime.z() * ray_prime.z() - 0.25 * origin_h * ray_h;
+        let c = origin_prime.x() * origin_prime.x() + origin_prime.z() * origin_prime.z() - 0.25 * origin_h * origin_h;
+
+        let mut crossings = Vec::<(f64, Vector4)>::with_capacity(3);
+        add_side_collisions(&mut crossings, origin_prime, ray_prime, a, b, c, |pt| Vector4::vec(pt.x(), 0.25 * (1.0 - pt.y()), pt.z()));
+        if self.capped {
+            add_cap_collisions(&mut crossings, origin_prime, ray_prime, -1.0, 1.0);
+        }
+        return crossings;
+    }
+}
+impl Shape for Cone{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.spatial_props.bounding_box(&BoundingBox::unit());
+    }
+    fn is_solid(&self) -> bool{
+        return self.capped;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+        return crossing_intervals(self.crossings(&origin_prime, &ray_prime), &self.spatial_props, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+        let crossings = self.crossings(&origin_prime, &ray_prime);
+        return closest_crossing(&crossings, &self.spatial_props, origin, ray, &ray_prime, min, max);
+    }
+}
+impl fmt::Display for Cone{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ends = if self.capped { "Capped" } else { "Open" };
+        write!(f, "{ends} cone with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.spatial_props.scale, self.spatial_props.r_x, self.spatial_props.r_y, self.spatial_props.r_z, self.lighting_props.color, self.spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
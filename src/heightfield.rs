@@ -0,0 +1,249 @@
+use std::fmt;
+use std::io;
+use std::io::{Error, ErrorKind};
+use crate::matrix::Vector4;
+use crate::shape::{LightingProps,Shape,Hit,read_props_from_tokens};
+use crate::polygon::SpatialProps;
+use crate::bounding_box::BoundingBox;
+
+//How far outside of a triangle or cell a ray may pass while still being tested against it,
+//so that rays through the shared edges of neighbouring triangles do not slip between them due to rounding errors.
+const EDGE_EPSILON : f64 = 0.000000001;
+
+//A terrain made from a grid of heights read from a grayscale image.
+//In object space the grid covers -1 to 1 along the X and Z axes, with the columns of the image along X and its rows along Z.
+//Black pixels have a height of 0.0 and white pixels a height of 1.0.
+//Each cell of the grid is split into two triangles, which are found by walking the ray through the cells it passes over.
+pub struct Heightfield{
+    pub spatial_props: SpatialProps,
+    pub lighting_props: LightingProps,
+    width: usize,
+    depth: usize,
+    heights: Vec<f64>,
+    //Object space normals at each sample, interpolated across the triangles for smooth shading.
+    normals: Vec<Vector4>,
+    object_bounds: BoundingBox,
+}
+
+impl Heightfield{
+    pub fn new(spatial_props: SpatialProps, lighting_props: LightingProps, width: usize, depth: usize, heights: Vec<f64>) -> Self{
+        let mut min_height = f64::INFINITY;
+        let mut max_height = f64::NEG_INFINITY;
+        for height in heights.iter(){
+            min_height = min_height.min(*height);
+            max_height = max_height.max(*height);
+        }
+        let object_bounds = BoundingBox::new(Vector4::point(-1.0, min_height, -1.0), Vector4::point(1.0, max_height, 1.0));
+
+        let mut heightfield = Self{spatial_props, lighting_props, width, depth, heights, normals: Vec::new(), object_bounds};
+        heightfield.normals = heightfield.sample_normals();
+        return heightfield;
+    }
+    //Reads a HEIGHTFIELD line, which has the same layout as the other shapes with the path of the image after the name.
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Result<Self, io::Error>{
+        if tokens.len() < 3 {
+            return Err(Error::new(ErrorKind::Other, format!("Could not read heightfield from {}.", tokens.join(" "))));
+        }
+        let mut shape_tokens = tokens.clone();
+        let image_file = shape_tokens.remove(2);
+        let (spatial_props, lighting_props) = match read_props_from_tokens(&shape_tokens) {
+            None => return Err(Error::new(ErrorKind::Other, format!("Could not read heightfield from {}.", tokens.join(" ")))),
+            Some(props) => props,
+        };
+
+        let image = match image::open(image_file) {
+            Err(e) => return Err(Error::new(ErrorKind::Other, format!("Couldn't read heightfield image {image_file}: {e}"))),
+            Ok(image) => image.into_luma16(),
+        };
+        let (width, depth) = (image.width() as usize, image.height() as usize);
+        if width < 2 || depth < 2 {
+            return Err(Error::new(ErrorKind::Other, format!("Heightfield image {image_file} must be at least 2 pixels wide and high.")));
+        }
+        let heights = image.pixels().map(|pixel| pixel.0[0] as f64 / u16::MAX as f64).collect();
+        return Ok(Self::new(spatial_props, lighting_props, width, depth, heights));
+    }
+
+    fn height(&self, i: usize, j: usize) -> f64{
+        return self.heights[j * self.width + i];
+    }
+    fn cell_size(&self) -> (f64, f64){
+        return (2.0 / (self.width - 1) as f64, 2.0 / (self.depth - 1) as f64);
+    }
+    fn sample_point(&self, i: usize, j: usize) -> Vector4{
+        let (cell_x, cell_z) = self.cell_size();
+        return Vector4::point(-1.0 + i as f64 * cell_x, self.height(i, j), -1.0 + j as f64 * cell_z);
+    }
+    //Estimates the normal at each sample from the slope between its neighbours.
+    fn sample_normals(&self) -> Vec<Vector4>{
+        let (cell_x, cell_z) = self.cell_size();
+        let mut normals = Vec::<Vector4>::with_capacity(self.heights.len());
+        for j in 0..self.depth{
+            for i in 0..self.width{
+                let (left, right) = (i.saturating_sub(1), (i + 1).min(self.width - 1));
+                let (back, front) = (j.saturating_sub(1), (j + 1).min(self.depth - 1));
+                let slope_x = (self.height(right, j) - self.height(left, j)) / ((right - left) as f64 * cell_x);
+                let slope_z = (self.height(i, front) - self.height(i, back)) / ((front - back) as f64 * cell_z);
+                let mut normal = Vector4::vec(-slope_x, 1.0, -slope_z);
+                normal.normalize();
+                normals.push(normal);
+            }
+        }
+        return normals;
+    }
+
+    //Finds the t and interpolated object space normal of a collision between a ray and the triangle with the given samples as corners,
+    //and whether the ray hit the top of the triangle.
+    fn check_triangle(&self, corners: [(usize, usize);3], origin_prime: &Vector4, ray_prime: &Vector4, min: f64, max: f64) -> Option<(f64, Vector4, bool)>{
+        let [a, b, c] = corners.map(|(i, j)| self.sample_point(i, j));
+        let mut ab = b.clone();
+        ab -= &a;
+        let mut ac = c.clone();
+        ac -= &a;
+        let p = ray_prime.cross(&ac);
+        let det = ab.dot(&p);
+        if det == 0.0 {
+            return None;
+        }
+        let mut to_origin = origin_prime.clone();
+        to_origin -= &a;
+        let u = to_origin.dot(&p) / det;
+        if u < -EDGE_EPSILON || u > 1.0 + EDGE_EPSILON {
+            return None;
+        }
+        let q = to_origin.cross(&ab);
+        let v = ray_prime.dot(&q) / det;
+        if v < -EDGE_EPSILON || u + v > 1.0 + EDGE_EPSILON {
+            return None;
+        }
+        let t = ac.dot(&q) / det;
+        if !(t > min && t < max) {
+            return None;
+        }
+
+        let mut normal = Vector4::zero();
+        for ((i, j), weight) in corners.iter().zip([1.0 - u - v, u, v]){
+            let mut weighted = self.normals[j * self.width + i].clone();
+            weighted *= weight;
+            normal += &weighted;
+        }
+        //Rays hitting the terrain from below see its underside.
+        let from_above = ac.cross(&ab).dot(ray_prime) <= 0.0;
+        if !from_above {
+            normal *= -1.0;
+        }
+        return Some((t, normal, from_above));
+    }
+
+    //Walks the ray through the cells of the grid in order, returning the first collision found along with the index of the triangle hit.
+    //The triangles of each cell are numbered in pairs, with the cells in the same order as the samples.
+    fn check_grid(&self, origin_prime: &Vector4, ray_prime: &Vector4, min: f64, max: f64) -> Option<(f64, Vector4, bool, usize)>{
+        let inv_ray = Vector4::vec(1.0 / ray_prime.x(), 1.0 / ray_prime.y(), 1.0 / ray_prime.z());
+        let (t_start, t_end) = self.object_bounds.check_collision(origin_prime, &inv_ray, min, max)?;
+        let (cell_x, cell_z) = self.cell_size();
+
+        let mut start = ray_prime.clone();
+        start *= t_start;
+        start += origin_prime;
+        let mut i = (((start.x() + 1.0) / cell_x).floor().max(0.0) as usize).min(self.width - 2);
+        let mut j = (((start.z() + 1.0) / cell_z).floor().max(0.0) as usize).min(self.depth - 2);
+
+        //For each axis, the t at which the ray crosses into the next cell and the change in t from one cell to the next.
+        let step = |position: f64, direction: f64, index: usize, size: f64| -> (f64, f64) {
+            if direction == 0.0 {
+                return (f64::INFINITY, f64::INFINITY);
+            }
+            let boundary = match direction > 0.0 {
+                true => -1.0 + (index + 1) as f64 * size,
+                false => -1.0 + index as f64 * size,
+            };
+            return ((boundary - position) / direction, size / direction.abs());
+        };
+        let (mut next_x, delta_x) = step(origin_prime.x(), ray_prime.x(), i, cell_x);
+        let (mut next_z, delta_z) = step(origin_prime.z(), ray_prime.z(), j, cell_z);
+
+        let mut t_cell = t_start;
+        loop {
+            //Cells that the ray passes entirely above or below are skipped.
+            let t_exit = next_x.min(next_z).min(t_end);
+            let corners = [self.height(i, j), self.height(i + 1, j), self.height(i, j + 1), self.height(i + 1, j + 1)];
+            let cell_min = corners.iter().cloned().fold(f64::INFINITY, f64::min);
+            let cell_max = corners.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let (y_enter, y_exit) = (origin_prime.y() + t_cell * ray_prime.y(), origin_prime.y() + t_exit * ray_prime.y());
+            if !(y_enter.min(y_exit) > cell_max + EDGE_EPSILON || y_enter.max(y_exit) < cell_min - EDGE_EPSILON) {
+                let first = self.check_triangle([(i, j), (i + 1, j), (i + 1, j + 1)], origin_prime, ray_prime, min, max);
+                let second = self.check_triangle([(i, j), (i + 1, j + 1), (i, j + 1)], origin_prime, ray_prime, min, max);
+                let cell_face = 2 * (j * (self.width - 1) + i);
+                let hit = match (first, second) {
+                    (Some(first), Some(second)) => Some(if first.0 <= second.0 { (first, cell_face) } else { (second, cell_face + 1) }),
+                    (Some(first), None) => Some((first, cell_face)),
+                    (None, Some(second)) => Some((second, cell_face + 1)),
+                    (None, None) => None,
+                };
+                if let Some(((t, normal, from_above), face_id)) = hit {
+                    return Some((t, normal, from_above, face_id));
+                }
+            }
+
+            if t_exit >= t_end {
+                return None;
+            }
+            if next_x < next_z {
+                if (ray_prime.x() > 0.0 && i + 2 >= self.width) || (ray_prime.x() < 0.0 && i == 0) {
+                    return None;
+                }
+                i = if ray_prime.x() > 0.0 { i + 1 } else { i - 1 };
+                t_cell = next_x;
+                next_x += delta_x;
+            }
+            else {
+                if (ray_prime.z() > 0.0 && j + 2 >= self.depth) || (ray_prime.z() < 0.0 && j == 0) {
+                    return None;
+                }
+                j = if ray_prime.z() > 0.0 { j + 1 } else { j - 1 };
+                t_cell = next_z;
+                next_z += delta_z;
+            }
+        }
+    }
+}
+
+impl Shape for Heightfield{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.spatial_props.bounding_box(&self.object_bounds);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+        let (t, normal_prime, from_above, face_id) = self.check_grid(&origin_prime, &ray_prime, min, max)?;
+
+        let mut col_pt = ray.clone();
+        col_pt *= t;
+        col_pt += origin;
+        col_pt.force_point();
+        let mut hit = Hit::new(t, col_pt, normal_prime.apply_inv_transpose(&self.spatial_props.inv_transp), from_above);
+        //The image is stretched over the terrain, with u along its columns and v along its rows.
+        let mut col_pt_prime = ray_prime.clone();
+        col_pt_prime *= t;
+        col_pt_prime += &origin_prime;
+        let mut tangent_prime = Vector4::vec(1.0, 0.0, 0.0);
+        let mut along_normal = normal_prime.clone();
+        along_normal *= normal_prime.x() / normal_prime.len_sq();
+        tangent_prime -= &along_normal;
+        hit.set_uv((0.5 * (col_pt_prime.x() + 1.0), 0.5 * (col_pt_prime.z() + 1.0)), &tangent_prime, &self.spatial_props);
+        hit.face_id = face_id;
+        return Some(hit);
+    }
+}
+
+impl fmt::Display for Heightfield{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Heightfield of {}x{} samples with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.width, self.depth, self.spatial_props.scale, self.spatial_props.r_x, self.spatial_props.r_y, self.spatial_props.r_z, self.lighting_props.color, self.spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
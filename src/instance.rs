@@ -0,0 +1,112 @@
+use std::fmt;
+use std::sync::Arc;
+use crate::matrix::Vector4;
+use crate::shape::{LightingProps,Shape,Interval,Hit};
+use crate::polygon::SpatialProps;
+use crate::bounding_box::BoundingBox;
+use crate::bvh::Bvh;
+use crate::csg::{CsgOperation,combine_intervals};
+
+//A named group of shapes that is declared once and placed in the scene any number of times by instances.
+//The shapes are given in the object's own space, and every instance shares them along with their hierarchy.
+pub struct SharedObject{
+    pub name: String,
+    pub shapes: Vec<Box<dyn Shape>>,
+    bvh: Bvh,
+}
+
+impl SharedObject{
+    pub fn new(name: String, shapes: Vec<Box<dyn Shape>>) -> Self{
+        let boxes: Vec<BoundingBox> = shapes.iter().map(|shape| shape.bounding_box()).collect();
+        let bvh = Bvh::new(&boxes);
+        return Self{name, shapes, bvh};
+    }
+}
+
+//A placement of a shared object with its own position, scale and rotation.
+//Without lighting properties of its own, each shape of the instance keeps the lighting properties it was declared with.
+pub struct Instance{
+    pub object: Arc<SharedObject>,
+    pub spatial_props: SpatialProps,
+    pub lighting_props: Option<LightingProps>,
+}
+
+impl Instance{
+    pub fn new(object: Arc<SharedObject>, spatial_props: SpatialProps, lighting_props: Option<LightingProps>) -> Self{
+        return Self{object, spatial_props, lighting_props};
+    }
+}
+
+impl Shape for Instance{
+    fn lighting_props(&self) -> &LightingProps{
+        return match &self.lighting_props {
+            Some(lighting_props) => lighting_props,
+            None => self.object.shapes[0].lighting_props(),
+        };
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        let object_bounds = self.object.bvh.bounds();
+        if !object_bounds.is_finite() {
+            return BoundingBox::infinite();
+        }
+        return self.spatial_props.bounding_box(&object_bounds);
+    }
+    //An instance made up only of solids is the union of them.
+    fn is_solid(&self) -> bool{
+        return self.object.shapes.iter().all(|shape| shape.is_solid());
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+
+        //Moving the ray into object space does not change t, so only the normals need to be moved back.
+        let mut intervals = Vec::<Interval>::new();
+        for shape in self.object.shapes.iter(){
+            intervals = combine_intervals(&intervals, &shape.check_intervals(&origin_prime, &ray_prime), CsgOperation::Union);
+        }
+        for interval in intervals.iter_mut(){
+            interval.enter.1 = interval.enter.1.apply_inv_transpose(&self.spatial_props.inv_transp);
+            interval.exit.1 = interval.exit.1.apply_inv_transpose(&self.spatial_props.inv_transp);
+        }
+        return intervals;
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        return self.check_lit_collision(origin, ray, min, max).map(|(hit, _lighting_props)| hit);
+    }
+    fn check_lit_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<(Hit,&LightingProps)>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+
+        let (_t, (mut hit, lighting_props)) = self.object.bvh.closest_collision(&origin_prime, &ray_prime, min, max, |index, min, max| {
+            return self.object.shapes[index].check_lit_collision(&origin_prime, &ray_prime, min, max).map(|(mut hit, lighting_props)| {
+                hit.add_shape_index(index, self.object.shapes.len());
+                return (hit.t, (hit, lighting_props));
+            });
+        })?;
+
+        //The hit is found in object space, so everything but t, the texture coordinates and the face is moved back into world space.
+        let mut col_pt = ray.clone();
+        col_pt *= hit.t;
+        col_pt += origin;
+        col_pt.force_point();
+        hit.point = col_pt;
+        hit.normal = hit.normal.apply_inv_transpose(&self.spatial_props.inv_transp);
+        let mut tangent = &self.spatial_props.matrix * &hit.tangent;
+        tangent.force_vec();
+        hit.tangent = tangent;
+        return Some((hit, self.lighting_props.as_ref().unwrap_or(lighting_props)));
+    }
+}
+
+impl fmt::Display for Instance{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Instance of {} ({} shapes) with scale {}, rotation X:{} Y:{} Z:{} located at {}.", self.object.name, self.object.shapes.len(), self.spatial_props.scale, self.spatial_props.r_x, self.spatial_props.r_y, self.spatial_props.r_z, self.spatial_props.pos)?;
+        return match &self.lighting_props {
+            None => Ok(()),
+            Some(lighting_props) => write!(f, "\nColor {} and lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", lighting_props.color, lighting_props.amb, lighting_props.diff, lighting_props.spec, lighting_props.refl, lighting_props.bright),
+        };
+    }
+}
@@ -2,10 +2,27 @@ use std::env;
 
 mod matrix;
 mod polygon;
+mod convex_hull;
 mod shape;
 use crate::matrix::Matrix4;
 mod elements;
 mod mesh_shape;
+mod mesh_loader;
+mod bounding_box;
+mod bvh;
+mod polynomial;
+mod torus;
+mod quadric;
+mod sweep;
+mod bezier;
+mod texture;
+mod pattern;
+mod pbr;
+mod material;
+mod csg;
+mod heightfield;
+mod sdf;
+mod instance;
 mod renderdata;
 use crate::renderdata::RenderData;
 
@@ -0,0 +1,223 @@
+use std::fmt;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+use crate::matrix::Vector4;
+use crate::shape::{LightingProps,Shape,Interval,Hit};
+use crate::polygon::SpatialProps;
+use crate::bounding_box::BoundingBox;
+use crate::texture::{ImageTexture,WrapMode,Filter};
+use crate::pattern::{Pattern,PatternKind,PatternTarget};
+use crate::pbr::ShadingModel;
+
+//The keywords that start the material options, which may follow the lighting coefficients at the end of any shape line.
+const MATERIAL_KEYWORDS : [&str;6] = ["TEXTURE", "PATTERN", "BUMP", "NORMALMAP", "TRANSPARENT", "PBR"];
+
+//Splits the tokens of a shape line into those describing the shape and the material options after them.
+pub fn split_material_tokens<'a>(tokens: &[&'a str]) -> (Vec<&'a str>, Vec<&'a str>){
+    let start = tokens.iter().skip(2).position(|token| MATERIAL_KEYWORDS.contains(token)).map(|index| index + 2);
+    return match start {
+        None => (tokens.to_vec(), Vec::new()),
+        Some(start) => (tokens[..start].to_vec(), tokens[start..].to_vec()),
+    };
+}
+
+//Loads an image used by a material option, reading the REPEAT or CLAMP and BILINEAR or NEAREST options that may follow it
+//from the given token onwards. Returns the image and the index of the first token after its options.
+fn read_image(tokens: &[&str], filename: &str, mut next: usize) -> Result<(ImageTexture, usize), io::Error>{
+    let (mut wrap, mut filter) = (WrapMode::Repeat, Filter::Bilinear);
+    while let Some(option) = tokens.get(next) {
+        match *option {
+            "REPEAT" => wrap = WrapMode::Repeat,
+            "CLAMP" => wrap = WrapMode::Clamp,
+            "BILINEAR" => filter = Filter::Bilinear,
+            "NEAREST" => filter = Filter::Nearest,
+            &_ => break,
+        }
+        next += 1;
+    }
+    return Ok((ImageTexture::load(filename, wrap, filter)?, next));
+}
+
+//Reads the material options of a shape line on top of the lighting properties of the shape.
+//An image texture is given by TEXTURE and the path of the image, optionally followed by REPEAT or CLAMP
+//and by BILINEAR or NEAREST. A procedural pattern is given by PATTERN, the property it drives, the kind of pattern
+//and its scale, followed by its low and high colours for COLOR or its low and high values for the coefficients.
+//A bump map is given by BUMP, the path of a height image and the strength of the bumps, and a normal map by
+//NORMALMAP and the path of the image. Both may be followed by the same options as a texture.
+//Transparency is given by TRANSPARENT, how much light passes through the surface and the index of refraction of the shape.
+//Physically based shading is given by PBR, the roughness and the metallic value of the surface.
+pub fn read_material(tokens: &[&str], lighting_props: &LightingProps) -> Result<LightingProps, io::Error>{
+    let mut lighting_props = lighting_props.clone();
+    let mut next = 0;
+    while next < tokens.len() {
+        match tokens[next] {
+            "TEXTURE" => {
+                let filename = match tokens.get(next + 1) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("TEXTURE must be followed by the path of an image: {}.", tokens.join(" ")))),
+                    Some(filename) => *filename,
+                };
+                let (texture, options_end) = read_image(tokens, filename, next + 2)?;
+                lighting_props.texture = Some(Arc::new(texture));
+                next = options_end;
+            },
+            "BUMP" => {
+                let (filename, strength) = match (tokens.get(next + 1), tokens.get(next + 2).and_then(|token| token.trim().parse::<f64>().ok())) {
+                    (Some(filename), Some(strength)) => (*filename, strength),
+                    _ => return Err(Error::new(ErrorKind::Other, format!("BUMP must be followed by the path of an image and a strength: {}.", tokens.join(" ")))),
+                };
+                let (height_map, options_end) = read_image(tokens, filename, next + 3)?;
+                lighting_props.bump_map = Some((Arc::new(height_map), strength));
+                next = options_end;
+            },
+            "NORMALMAP" => {
+                let filename = match tokens.get(next + 1) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("NORMALMAP must be followed by the path of an image: {}.", tokens.join(" ")))),
+                    Some(filename) => *filename,
+                };
+                let (normal_map, options_end) = read_image(tokens, filename, next + 2)?;
+                lighting_props.normal_map = Some(Arc::new(normal_map));
+                next = options_end;
+            },
+            "TRANSPARENT" => {
+                let values = tokens.get((next + 1)..(next + 3)).map(|values| values.iter().map(|token| token.trim().parse::<f64>().ok()).collect::<Option<Vec<f64>>>());
+                match values {
+                    Some(Some(values)) if (0.0..=1.0).contains(&values[0]) && values[1] > 0.0 => {
+                        lighting_props.transp = values[0];
+                        lighting_props.ior = values[1];
+                    },
+                    _ => return Err(Error::new(ErrorKind::Other, format!("TRANSPARENT must be followed by a transparency from 0 to 1 and a positive index of refraction: {}.", tokens.join(" ")))),
+                }
+                next += 3;
+            },
+            "PBR" => {
+                let values = tokens.get((next + 1)..(next + 3)).map(|values| values.iter().map(|token| token.trim().parse::<f64>().ok()).collect::<Option<Vec<f64>>>());
+                match values {
+                    Some(Some(values)) if (0.0..=1.0).contains(&values[0]) && (0.0..=1.0).contains(&values[1]) => {
+                        lighting_props.model = ShadingModel::Pbr{roughness: values[0], metallic: values[1]};
+                    },
+                    _ => return Err(Error::new(ErrorKind::Other, format!("PBR must be followed by a roughness and a metallic value from 0 to 1: {}.", tokens.join(" ")))),
+                }
+                next += 3;
+            },
+            "PATTERN" => {
+                let pattern_error = || Error::new(ErrorKind::Other, format!("Could not read pattern from {}.", tokens[next..].join(" ")));
+                if tokens.len() < next + 4 {
+                    return Err(pattern_error());
+                }
+                let target = PatternTarget::from_token(tokens[next + 1]).ok_or_else(pattern_error)?;
+                let kind = PatternKind::from_token(tokens[next + 2]).ok_or_else(pattern_error)?;
+                let value_count = if target == PatternTarget::Color { 7 } else { 3 };
+                if tokens.len() < next + 3 + value_count {
+                    return Err(pattern_error());
+                }
+                let mut values = Vec::<f64>::with_capacity(value_count);
+                for token in tokens[(next + 3)..(next + 3 + value_count)].iter(){
+                    values.push(token.trim().parse::<f64>().map_err(|_e| pattern_error())?);
+                }
+                let (low, high) = match target {
+                    PatternTarget::Color => (Vector4::vec_from_slice(&values[1..4]), Vector4::vec_from_slice(&values[4..7])),
+                    _ => (Vector4::vec(values[1], values[1], values[1]), Vector4::vec(values[2], values[2], values[2])),
+                };
+                lighting_props.patterns.push((target, Pattern{kind, scale: values[0], low, high}));
+                next += 3 + value_count;
+            },
+            token => return Err(Error::new(ErrorKind::Other, format!("Unknown material option {token} in {}.", tokens.join(" ")))),
+        }
+    }
+    return Ok(lighting_props);
+}
+
+//A shape with material options, which replace the lighting properties of every surface of the shape.
+pub struct MaterialShape{
+    shape: Box<dyn Shape>,
+    lighting_props: LightingProps,
+}
+
+impl MaterialShape{
+    pub fn new(shape: Box<dyn Shape>, lighting_props: LightingProps) -> Self{
+        return Self{shape, lighting_props};
+    }
+}
+
+impl Shape for MaterialShape{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return self.shape.spatial_props();
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.shape.bounding_box();
+    }
+    fn is_solid(&self) -> bool{
+        return self.shape.is_solid();
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        return self.shape.check_intervals(origin, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        let mut hit = self.shape.check_collision(origin, ray, min, max)?;
+        if let Some(spatial_props) = self.shape.spatial_props() {
+            let mut local_point = &spatial_props.inv_matrix * &hit.point;
+            local_point.force_point();
+            hit.local_point = local_point;
+        }
+        return Some(hit);
+    }
+}
+
+impl fmt::Display for MaterialShape{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.shape)?;
+        if let Some(texture) = &self.lighting_props.texture {
+            write!(f, "\n{}.", texture)?;
+        }
+        if let Some(normal_map) = &self.lighting_props.normal_map {
+            write!(f, "\nNormal map: {}.", normal_map)?;
+        }
+        if let Some((height_map, strength)) = &self.lighting_props.bump_map {
+            write!(f, "\nBump map of strength {}: {}.", strength, height_map)?;
+        }
+        if self.lighting_props.model != ShadingModel::Phong {
+            write!(f, "\n{}.", self.lighting_props.model)?;
+        }
+        if self.lighting_props.transp > 0.0 {
+            write!(f, "\nTransparency: {} with index of refraction {}.", self.lighting_props.transp, self.lighting_props.ior)?;
+        }
+        for (target, pattern) in self.lighting_props.patterns.iter(){
+            write!(f, "\n{} pattern of scale {} from {} to {} driving the {}.", pattern.kind, pattern.scale, pattern.low, pattern.high, target)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elements::Plane;
+
+    //The object space point that patterns see where a ray straight down through the given point hits the plane.
+    fn pattern_point(plane_line: &str, x: f64, z: f64) -> Vector4{
+        let tokens: Vec<&str> = plane_line.split_whitespace().collect();
+        let plane = Plane::read_from_tokens(&tokens).unwrap();
+        let lighting_props = plane.lighting_props.clone();
+        let shape = MaterialShape::new(Box::new(plane), lighting_props);
+        let hit = shape.check_collision(&Vector4::point(x, 10.0, z), &Vector4::vec(0.0, -1.0, 0.0), 0.0, f64::INFINITY).unwrap();
+        return hit.local_point;
+    }
+
+    #[test]
+    fn patterns_on_planes_follow_the_plane(){
+        //A plane that is moved up by 1, stretched by 2 along x and turned a quarter turn about y, which takes x to -z.
+        let local = pattern_point("PLANE floor 0 1 0 2 1 1 0 1.5707963267948966 0 1 1 1 0.2 0.7 0.2 0 20", 0.0, 4.0);
+        assert!((local.x() + 2.0).abs() < 0.000001 && local.y().abs() < 0.000001 && local.z().abs() < 0.000001);
+
+        //A plane through (0, 1, 0) given by its normal straight up, which has the same space as the plane moved up by 1.
+        let local = pattern_point("PLANE floor NORMAL 0 1 0 0 1 0 1 1 1 0.2 0.7 0.2 0 20", 3.0, -2.0);
+        assert!((local.x() - 3.0).abs() < 0.000001 && local.y().abs() < 0.000001 && (local.z() + 2.0).abs() < 0.000001);
+        //A tilted plane keeps the points that are hit at y = 0 of its space.
+        let local = pattern_point("PLANE ramp NORMAL 0 0 0 1 1 1 1 1 1 0.2 0.7 0.2 0 20", 0.5, 0.25);
+        assert!(local.y().abs() < 0.000001);
+    }
+}
@@ -293,6 +293,17 @@ impl ops::Add<&Vector4> for &Vector4{
     }
 }
 
+impl ops::Sub<&Vector4> for &Vector4{
+    type Output = Vector4;
+    fn sub(self, rhs: &Vector4) -> Vector4{
+        let mut new: Vector4 = self.clone(); 
+        for i in 0..4{
+            new.arr[i] -= rhs.arr[i];
+        }
+        return new;
+    }
+}
+
 impl ops::MulAssign<f64> for Vector4{
     fn mul_assign(&mut self, rhs: f64){
         for i in 0..4{
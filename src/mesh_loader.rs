@@ -0,0 +1,573 @@
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::collections::HashMap;
+use crate::matrix::Vector4;
+use crate::polygon::{SpatialProps,Polygon};
+
+//Resolves a 1-based (or negative, relative to the end) OBJ index into a 0-based index.
+fn resolve_index(token: &str, count: usize) -> Option<usize>{
+    let index = token.trim().parse::<i64>().ok()?;
+    let resolved = match index {
+        0 => return None,
+        i if i > 0 => i - 1,
+        i => count as i64 + i,
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return None;
+    }
+    return Some(resolved as usize);
+}
+
+fn parse_floats(tokens: &[&str], count: usize) -> Option<Vec<f64>>{
+    if tokens.len() < count {
+        return None;
+    }
+    let mut values = Vec::<f64>::with_capacity(count);
+    for token in tokens[..count].iter(){
+        values.push(token.trim().parse::<f64>().ok()?);
+    }
+    return Some(values);
+}
+
+//A triangle of a mesh file, as indices into the vertex positions and, if the file gives them, its vertex normals and texture coordinates.
+struct Triangle{
+    positions: [usize;3],
+    normals: Option<[usize;3]>,
+    uvs: Option<[usize;3]>,
+}
+
+//Adds a triangle to the list unless it is degenerate.
+fn push_triangle(triangles: &mut Vec<Triangle>, positions: &[Vector4], corners: [usize;3], normals: Option<[usize;3]>, uvs: Option<[usize;3]>){
+    if Polygon::winding_normal(&corners.map(|index| positions[index])).len_sq() <= 0.0 {
+        return;
+    }
+    triangles.push(Triangle{positions: corners, normals, uvs});
+}
+
+//Splits a face, given as indices into the positions, into triangles given as indices into the face.
+fn face_triangles(positions: &[Vector4], face: &[usize]) -> Vec<[usize;3]>{
+    if face.len() == 3 {
+        return vec![[0, 1, 2]];
+    }
+    let points: Vec<Vector4> = face.iter().map(|index| positions[*index]).collect();
+    return Polygon::triangulate(&points);
+}
+
+//Reads a mesh file into polygons placed with the given spatial properties, choosing the format from the extension of the file:
+//.stl for STL, .ply for PLY and anything else for Wavefront .obj.
+//Faces with more than three vertices are split into triangles, which works for concave faces as well, and degenerate faces are skipped.
+//Faces with vertex normals in the file are shaded smoothly with them. If a crease angle is given,
+//the other faces are shaded smoothly with normals averaged from the faces around each vertex.
+//Vertex colours in a PLY file are blended across each face.
+pub fn load_mesh(filename: &str, spatial_props: &SpatialProps, crease_angle: Option<f64>) -> Result<Vec<Polygon>, io::Error>{
+    let extension = Path::new(filename).extension().and_then(|extension| extension.to_str()).unwrap_or("").to_lowercase();
+    return match extension.as_str() {
+        "stl" => load_stl(filename, spatial_props, crease_angle),
+        "ply" => load_ply(filename, spatial_props, crease_angle),
+        _ => load_obj(filename, spatial_props, crease_angle),
+    };
+}
+
+//Reads a Wavefront .obj file.
+fn load_obj(filename: &str, spatial_props: &SpatialProps, crease_angle: Option<f64>) -> Result<Vec<Polygon>, io::Error>{
+    let path = Path::new(filename);
+    let file = match File::open(path) {
+        Err(_e) => return Err(Error::new(ErrorKind::Other, format!("Couldn't open mesh file {filename} for reading!"))),
+        Ok(file) => file,
+    };
+    let reader = BufReader::new(file);
+
+    let mut positions = Vec::<Vector4>::new();
+    let mut uvs = Vec::<(f64, f64)>::new();
+    let mut normals = Vec::<Vector4>::new();
+    let mut triangles = Vec::<Triangle>::new();
+
+    for (line_index, line) in reader.lines().enumerate(){
+        let line_number = line_index + 1;
+        let line = match line {
+            Err(e) => return Err(Error::new(ErrorKind::Other, format!("{filename}:{line_number}: {e}"))),
+            Ok(line) => line,
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        match tokens[0] {
+            "v" => {
+                match parse_floats(&tokens[1..], 3) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("{filename}:{line_number}: Could not read vertex from {line}."))),
+                    Some(values) => positions.push(Vector4::point_from_slice(&values)),
+                }
+            },
+            "vt" => {
+                match parse_floats(&tokens[1..], 2) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("{filename}:{line_number}: Could not read texture coordinate from {line}."))),
+                    Some(values) => uvs.push((values[0], values[1])),
+                }
+            },
+            "vn" => {
+                match parse_floats(&tokens[1..], 3) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("{filename}:{line_number}: Could not read vertex normal from {line}."))),
+                    Some(values) => normals.push(Vector4::vec_from_slice(&values)),
+                }
+            },
+            "f" => {
+                if tokens.len() < 4 {
+                    return Err(Error::new(ErrorKind::Other, format!("{filename}:{line_number}: A face needs at least three vertices: {line}.")));
+                }
+                let mut face = Vec::<(usize, Option<usize>, Option<usize>)>::with_capacity(tokens.len() - 1);
+                for vertex in tokens[1..].iter(){
+                    //Each vertex is one of v, v/vt, v//vn or v/vt/vn.
+                    let mut indices = vertex.split('/');
+                    let position = indices.next().and_then(|token| resolve_index(token, positions.len()));
+                    let uv = match indices.next() {
+                        None | Some("") => Some(None),
+                        Some(token) => resolve_index(token, uvs.len()).map(Some),
+                    };
+                    let normal = match indices.next() {
+                        None | Some("") => Some(None),
+                        Some(token) => resolve_index(token, normals.len()).map(Some),
+                    };
+                    match (position, uv, normal) {
+                        (Some(index), Some(uv), Some(normal)) if indices.next().is_none() => face.push((index, uv, normal)),
+                        _ => return Err(Error::new(ErrorKind::Other, format!("{filename}:{line_number}: Invalid face vertex {vertex}."))),
+                    }
+                }
+
+                let face_positions: Vec<usize> = face.iter().map(|(position, _uv, _normal)| *position).collect();
+                for triangle in face_triangles(&positions, &face_positions){
+                    let corners = triangle.map(|index| face[index]);
+                    let normals = match corners {
+                        [(_, _, Some(a)), (_, _, Some(b)), (_, _, Some(c))] => Some([a, b, c]),
+                        _ => None,
+                    };
+                    let triangle_uvs = match corners {
+                        [(_, Some(a), _), (_, Some(b), _), (_, Some(c), _)] => Some([a, b, c]),
+                        _ => None,
+                    };
+                    push_triangle(&mut triangles, &positions, corners.map(|(position, _uv, _normal)| position), normals, triangle_uvs);
+                }
+            },
+            //Groups, materials, smoothing groups and other statements do not affect the geometry.
+            &_ => continue,
+        }
+    }
+
+    return build_mesh(filename, &positions, &normals, &uvs, &[], &triangles, spatial_props, crease_angle);
+}
+
+//Turns the triangles read from a mesh file into polygons, with smooth shading as described for load_mesh.
+//Colours are given per vertex position, and are only used if every vertex has one.
+fn build_mesh(filename: &str, positions: &[Vector4], normals: &[Vector4], uvs: &[(f64, f64)], colors: &[Vector4], triangles: &[Triangle], spatial_props: &SpatialProps, crease_angle: Option<f64>) -> Result<Vec<Polygon>, io::Error>{
+    if triangles.is_empty() {
+        return Err(Error::new(ErrorKind::Other, format!("{filename}: Mesh file contains no faces.")));
+    }
+
+    //The normal of each triangle and the triangles that share each vertex, for averaging normals.
+    let mut face_normals = Vec::<Vector4>::new();
+    let mut vertex_faces = Vec::<Vec<usize>>::new();
+    if crease_angle.is_some() {
+        face_normals = triangles.iter().map(|triangle| {
+            let mut normal = Polygon::winding_normal(&triangle.positions.map(|index| positions[index]));
+            normal.normalize();
+            return normal;
+        }).collect();
+        vertex_faces = vec![Vec::<usize>::new(); positions.len()];
+        for (face_index, triangle) in triangles.iter().enumerate(){
+            for index in triangle.positions.iter(){
+                vertex_faces[*index].push(face_index);
+            }
+        }
+    }
+
+    let mut polygons = Vec::<Polygon>::with_capacity(triangles.len());
+    for (face_index, triangle) in triangles.iter().enumerate(){
+        let mut polygon = Polygon::from_winding(triangle.positions.map(|index| positions[index]).to_vec(), spatial_props);
+        match (triangle.normals, crease_angle) {
+            (Some(normal_indices), _) => polygon.set_vertex_normals(normal_indices.map(|index| normals[index]).to_vec()),
+            (None, Some(crease_angle)) => {
+                let vertex_normals = triangle.positions.map(|index| {
+                    return average_normal(&face_normals, &vertex_faces[index], face_index, crease_angle);
+                });
+                polygon.set_vertex_normals(vertex_normals.to_vec());
+            },
+            (None, None) => {},
+        }
+        if let Some(uv_indices) = triangle.uvs {
+            polygon.set_vertex_uvs(uv_indices.map(|index| uvs[index]).to_vec());
+        }
+        if colors.len() == positions.len() {
+            polygon.set_vertex_colors(triangle.positions.map(|index| colors[index]).to_vec());
+        }
+        polygons.push(polygon);
+    }
+    return Ok(polygons);
+}
+
+//Averages the normals of the faces around a vertex that meet the given face at no more than the crease angle,
+//so that sharp edges stay sharp while gently curved surfaces are smoothed over.
+fn average_normal(face_normals: &[Vector4], faces: &[usize], face_index: usize, crease_angle: f64) -> Vector4{
+    let face_normal = &face_normals[face_index];
+    let min_cos = crease_angle.cos();
+    let mut normal = Vector4::zero();
+    for other in faces.iter(){
+        if face_normal.dot(&face_normals[*other]) >= min_cos {
+            normal += &face_normals[*other];
+        }
+    }
+    return normal;
+}
+
+fn read_file(filename: &str) -> Result<Vec<u8>, io::Error>{
+    return match std::fs::read(Path::new(filename)) {
+        Err(_e) => Err(Error::new(ErrorKind::Other, format!("Couldn't open mesh file {filename} for reading!"))),
+        Ok(data) => Ok(data),
+    };
+}
+
+//Reads an STL file, in either its binary or ASCII form. STL files list the corners of every triangle separately,
+//so corners at the same position are joined up again to let the mesh be shaded smoothly.
+fn load_stl(filename: &str, spatial_props: &SpatialProps, crease_angle: Option<f64>) -> Result<Vec<Polygon>, io::Error>{
+    let data = read_file(filename)?;
+    let mut positions = Vec::<Vector4>::new();
+    let mut position_indices = HashMap::<[u64;3], usize>::new();
+    let mut triangles = Vec::<Triangle>::new();
+    let mut add_corner = |coords: [f64;3], positions: &mut Vec<Vector4>| -> usize {
+        //Adding 0.0 turns -0.0 into 0.0 so that both give the same key.
+        let key = coords.map(|coord| (coord + 0.0).to_bits());
+        return *position_indices.entry(key).or_insert_with(|| {
+            positions.push(Vector4::point_from_slice(&coords));
+            return positions.len() - 1;
+        });
+    };
+
+    //Binary files start with an 80 byte header and a triangle count, which fixes the size of the file.
+    //ASCII files start with "solid", but so do the headers of some binary files, so the size is checked first.
+    let binary_count = match data.len() >= 84 {
+        true => u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize,
+        false => 0,
+    };
+    if data.len() >= 84 && data.len() == 84 + 50 * binary_count {
+        triangles.reserve(binary_count);
+        for record in data[84..].chunks_exact(50){
+            let value = |index: usize| {
+                let start = 12 + 4 * index;
+                return f32::from_le_bytes([record[start], record[start + 1], record[start + 2], record[start + 3]]) as f64;
+            };
+            let corners = [0, 1, 2].map(|corner| add_corner([value(3 * corner), value(3 * corner + 1), value(3 * corner + 2)], &mut positions));
+            push_triangle(&mut triangles, &positions, corners, None, None);
+        }
+    }
+    else {
+        let text = match std::str::from_utf8(&data) {
+            Err(_e) => return Err(Error::new(ErrorKind::Other, format!("{filename}: STL file is neither valid binary nor ASCII."))),
+            Ok(text) => text,
+        };
+        let mut tokens = text.split_ascii_whitespace();
+        let mut corners = Vec::<usize>::with_capacity(3);
+        while let Some(token) = tokens.next(){
+            match token {
+                "vertex" => {
+                    let mut coords = [0.0;3];
+                    for coord in coords.iter_mut(){
+                        match tokens.next().and_then(|token| token.parse::<f64>().ok()) {
+                            None => return Err(Error::new(ErrorKind::Other, format!("{filename}: Could not read STL vertex."))),
+                            Some(value) => *coord = value,
+                        }
+                    }
+                    corners.push(add_corner(coords, &mut positions));
+                },
+                "endloop" => {
+                    //Facets with more than three corners are split into triangles.
+                    for triangle in face_triangles(&positions, &corners){
+                        push_triangle(&mut triangles, &positions, triangle.map(|index| corners[index]), None, None);
+                    }
+                    corners.clear();
+                },
+                //Facet normals are worked out from the winding of the corners instead.
+                &_ => continue,
+            }
+        }
+    }
+    return build_mesh(filename, &positions, &[], &[], &[], &triangles, spatial_props, crease_angle);
+}
+
+#[derive(Copy, Clone)]
+enum PlyType{
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl PlyType{
+    fn from_token(token: &str) -> Option<Self>{
+        return match token {
+            "char" | "int8" => Some(Self::Int8),
+            "uchar" | "uint8" => Some(Self::UInt8),
+            "short" | "int16" => Some(Self::Int16),
+            "ushort" | "uint16" => Some(Self::UInt16),
+            "int" | "int32" => Some(Self::Int32),
+            "uint" | "uint32" => Some(Self::UInt32),
+            "float" | "float32" => Some(Self::Float32),
+            "double" | "float64" => Some(Self::Float64),
+            &_ => None,
+        };
+    }
+    fn size(&self) -> usize{
+        return match self {
+            Self::Int8 | Self::UInt8 => 1,
+            Self::Int16 | Self::UInt16 => 2,
+            Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+            Self::Float64 => 8,
+        };
+    }
+}
+
+struct PlyProperty{
+    name: String,
+    value_type: PlyType,
+    //For list properties, the type of the count that comes before the values.
+    count_type: Option<PlyType>,
+}
+
+struct PlyElement{
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+//Reads the values in the body of a PLY file, which is either whitespace separated text or packed binary.
+enum PlyReader<'a>{
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary{data: &'a [u8], position: usize, big_endian: bool},
+}
+
+impl PlyReader<'_>{
+    fn read(&mut self, value_type: PlyType) -> Option<f64>{
+        let (data, position, big_endian) = match self {
+            Self::Ascii(tokens) => return tokens.next()?.parse::<f64>().ok(),
+            Self::Binary{data, position, big_endian} => (data, position, *big_endian),
+        };
+        let size = value_type.size();
+        let mut bytes = [0u8;8];
+        bytes[..size].copy_from_slice(data.get(*position..*position + size)?);
+        *position += size;
+        if big_endian {
+            bytes[..size].reverse();
+        }
+        let value = match value_type {
+            PlyType::Int8 => bytes[0] as i8 as f64,
+            PlyType::UInt8 => bytes[0] as f64,
+            PlyType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            PlyType::UInt16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            PlyType::Int32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            PlyType::UInt32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            PlyType::Float32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            PlyType::Float64 => f64::from_le_bytes(bytes),
+        };
+        return Some(value);
+    }
+}
+
+//Reads a PLY file, in its ASCII or either of its binary forms. Vertex normals are used for smooth shading,
+//and the average of the vertex colours, if there are any, becomes the colour of the mesh.
+fn load_ply(filename: &str, spatial_props: &SpatialProps, crease_angle: Option<f64>) -> Result<Vec<Polygon>, io::Error>{
+    let data = read_file(filename)?;
+    let header_error = |message: &str| Error::new(ErrorKind::Other, format!("{filename}: {message}"));
+
+    //The header is text up to and including the end_header line.
+    let marker = b"end_header";
+    let header_end = match data.windows(marker.len()).position(|window| window == marker) {
+        None => return Err(header_error("PLY file has no end_header line.")),
+        Some(start) => match data[start..].iter().position(|byte| *byte == b'\n') {
+            None => data.len(),
+            Some(newline) => start + newline + 1,
+        },
+    };
+    let header = String::from_utf8_lossy(&data[..header_end]);
+    let mut header_lines = header.lines();
+    if header_lines.next().map(|line| line.trim()) != Some("ply") {
+        return Err(header_error("Not a PLY file."));
+    }
+
+    let mut format = None;
+    let mut elements = Vec::<PlyElement>::new();
+    for line in header_lines{
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", kind, _version] => format = Some(kind.to_string()),
+            ["element", name, count] => match count.parse::<usize>() {
+                Err(_e) => return Err(header_error(&format!("Could not read PLY element from {line}."))),
+                Ok(count) => elements.push(PlyElement{name: name.to_string(), count, properties: Vec::new()}),
+            },
+            ["property", "list", count_type, value_type, name] => {
+                let (count_type, value_type) = match (PlyType::from_token(count_type), PlyType::from_token(value_type)) {
+                    (Some(count_type), Some(value_type)) => (count_type, value_type),
+                    _ => return Err(header_error(&format!("Could not read PLY property from {line}."))),
+                };
+                match elements.last_mut() {
+                    None => return Err(header_error(&format!("PLY property before any element: {line}."))),
+                    Some(element) => element.properties.push(PlyProperty{name: name.to_string(), value_type, count_type: Some(count_type)}),
+                }
+            },
+            ["property", value_type, name] => {
+                let value_type = match PlyType::from_token(value_type) {
+                    None => return Err(header_error(&format!("Could not read PLY property from {line}."))),
+                    Some(value_type) => value_type,
+                };
+                match elements.last_mut() {
+                    None => return Err(header_error(&format!("PLY property before any element: {line}."))),
+                    Some(element) => element.properties.push(PlyProperty{name: name.to_string(), value_type, count_type: None}),
+                }
+            },
+            //Comments, object info and the end of the header.
+            _ => continue,
+        }
+    }
+
+    let body = &data[header_end..];
+    let mut reader = match format.as_deref() {
+        Some("ascii") => match std::str::from_utf8(body) {
+            Err(_e) => return Err(header_error("PLY file body is not valid ASCII.")),
+            Ok(text) => PlyReader::Ascii(text.split_ascii_whitespace()),
+        },
+        Some("binary_little_endian") => PlyReader::Binary{data: body, position: 0, big_endian: false},
+        Some("binary_big_endian") => PlyReader::Binary{data: body, position: 0, big_endian: true},
+        _ => return Err(header_error("Unknown or missing PLY format.")),
+    };
+
+    let mut positions = Vec::<Vector4>::new();
+    let mut normals = Vec::<Vector4>::new();
+    let mut colors = Vec::<Vector4>::new();
+    let mut triangles = Vec::<Triangle>::new();
+    let body_error = |element: &PlyElement| Error::new(ErrorKind::Other, format!("{filename}: Could not read PLY {} data.", element.name));
+    for element in elements.iter(){
+        let index_of = |name: &str| element.properties.iter().position(|property| property.name == name && property.count_type.is_none());
+        let position_indices = [index_of("x"), index_of("y"), index_of("z")];
+        let normal_indices = [index_of("nx"), index_of("ny"), index_of("nz")];
+        let color_indices = [index_of("red"), index_of("green"), index_of("blue")];
+        let list_index = element.properties.iter().position(|property| {
+            return property.count_type.is_some() && (property.name == "vertex_indices" || property.name == "vertex_index");
+        });
+        if element.name == "vertex" {
+            positions.reserve(element.count);
+        }
+
+        let mut values = vec![0.0; element.properties.len()];
+        let mut face = Vec::<usize>::new();
+        for _i in 0..element.count{
+            for (property_index, property) in element.properties.iter().enumerate(){
+                match property.count_type {
+                    None => values[property_index] = reader.read(property.value_type).ok_or_else(|| body_error(element))?,
+                    Some(count_type) => {
+                        let count = reader.read(count_type).ok_or_else(|| body_error(element))? as usize;
+                        //Other lists, such as texture coordinates, are read past without touching the face.
+                        let is_face = Some(property_index) == list_index;
+                        if is_face {
+                            face.clear();
+                        }
+                        for _j in 0..count{
+                            let value = reader.read(property.value_type).ok_or_else(|| body_error(element))?;
+                            if is_face {
+                                face.push(value as usize);
+                            }
+                        }
+                    },
+                }
+            }
+
+            match element.name.as_str() {
+                "vertex" => {
+                    let [Some(x), Some(y), Some(z)] = position_indices else {
+                        return Err(header_error("PLY vertices have no x, y and z properties."));
+                    };
+                    positions.push(Vector4::point(values[x], values[y], values[z]));
+                    if let [Some(x), Some(y), Some(z)] = normal_indices {
+                        normals.push(Vector4::vec(values[x], values[y], values[z]));
+                    }
+                    if let [Some(r), Some(g), Some(b)] = color_indices {
+                        //Integer colours run up to the largest value of their type, floating point colours up to 1.0.
+                        let scale = match element.properties[r].value_type {
+                            PlyType::Float32 | PlyType::Float64 => 1.0,
+                            PlyType::UInt16 | PlyType::Int16 => u16::MAX as f64,
+                            _ => u8::MAX as f64,
+                        };
+                        colors.push(Vector4::vec(values[r] / scale, values[g] / scale, values[b] / scale));
+                    }
+                },
+                "face" if list_index.is_some() => {
+                    if face.iter().any(|index| *index >= positions.len()) {
+                        return Err(Error::new(ErrorKind::Other, format!("{filename}: PLY face refers to a missing vertex.")));
+                    }
+                    //Faces are only given normals if every vertex has one.
+                    let has_normals = normals.len() == positions.len();
+                    for triangle in face_triangles(&positions, &face){
+                        let corners = triangle.map(|index| face[index]);
+                        push_triangle(&mut triangles, &positions, corners, if has_normals { Some(corners) } else { None }, None);
+                    }
+                },
+                //Edges and other elements do not affect the geometry.
+                &_ => {},
+            }
+        }
+    }
+
+    return build_mesh(filename, &positions, &normals, &[], &colors, &triangles, spatial_props, crease_angle);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh_shape::MeshShape;
+    use crate::shape::{LightingProps,Shape};
+
+    //Loads the text of a PLY file through a temporary file, placed at the origin.
+    fn load_ply_text(name: &str, ply: &str) -> Result<Vec<Polygon>, io::Error>{
+        let path = std::env::temp_dir().join(format!("rusttracer_{name}_{}.ply", std::process::id()));
+        std::fs::write(&path, ply).unwrap();
+        let mesh = load_mesh(&path.to_string_lossy(), &origin_props(), None);
+        let _ = std::fs::remove_file(&path);
+        return mesh;
+    }
+    fn origin_props() -> SpatialProps{
+        return SpatialProps::new(Vector4::point(0.0, 0.0, 0.0), Vector4::vec(1.0, 1.0, 1.0), 0.0, 0.0, 0.0);
+    }
+
+    #[test]
+    fn ply_faces_with_more_lists_load(){
+        //Two triangles as MeshLab writes them, with a list of texture coordinates after the vertex indices of each face.
+        let ply = "ply\nformat ascii 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 2\nproperty list uchar int vertex_indices\nproperty list uchar float texcoord\nend_header\n\
+            0 0 0\n1 0 0\n1 1 0\n0 1 0\n\
+            3 0 1 2 6 0 0 1 0 1 1\n3 0 2 3 6 0 0 1 1 0 1\n";
+        assert_eq!(load_ply_text("lists", ply).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn ply_vertex_colors_blend_across_faces(){
+        //A red corner and two blue ones, which average to mostly blue over the whole mesh.
+        let ply = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0 255 0 0\n1 0 0 0 0 255\n0 1 0 0 0 255\n3 0 1 2\n";
+        let polygons = load_ply_text("colors", ply).unwrap();
+        let mesh = MeshShape::new(origin_props(), LightingProps::new(Vector4::vec(0.0, 1.0, 0.0), 0.1, 0.9, 0.0, 0.0, 1.0), polygons);
+
+        let color_at = |x: f64, y: f64| {
+            let hit = mesh.check_collision(&Vector4::point(x, y, 1.0), &Vector4::vec(0.0, 0.0, -1.0), 0.0, f64::INFINITY).unwrap();
+            return mesh.lighting_props().surface_at(&hit).color;
+        };
+        //Halfway from the red corner to the middle of the far edge is half red and half blue.
+        let color = color_at(0.25, 0.25);
+        assert!((color.x() - 0.5).abs() < 1e-9 && color.y().abs() < 1e-9 && (color.z() - 0.5).abs() < 1e-9);
+        let color = color_at(0.01, 0.01);
+        assert!(color.x() > 0.95 && color.z() < 0.05);
+    }
+}
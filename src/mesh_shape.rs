@@ -1,88 +1,108 @@
-use crate::shape::{LightingProps,Shape};
+use crate::shape::{LightingProps,Shape,Interval,Hit,read_props_from_tokens,intervals_from_crossings};
 use crate::polygon::{SpatialProps,Polygon};
-//use std::fmt;
+use crate::mesh_loader;
+use crate::bounding_box::BoundingBox;
+use crate::bvh::Bvh;
+use std::fmt;
+use std::io;
+use std::io::{Error, ErrorKind};
 use crate::matrix::Vector4;
 
 pub struct MeshShape {
     pub spatial_props: SpatialProps,
     pub lighting_props: LightingProps,
     pub polygons: Vec<Polygon>,
+    //Hierarchy over the object space bounding boxes of the polygons.
+    bvh: Bvh,
 }
 
 impl MeshShape {
     pub fn new(spatial_props: SpatialProps, lighting_props: LightingProps, polygons: Vec<Polygon>) -> Self{
-        return Self{spatial_props, lighting_props, polygons};
+        let boxes: Vec<BoundingBox> = polygons.iter().map(|polygon| polygon.bounding_box()).collect();
+        let bvh = Bvh::new(&boxes);
+        return Self{spatial_props, lighting_props, polygons, bvh};
     }
-    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<(SpatialProps,LightingProps)>{
-        if tokens.len() < 16 || tokens.len() > 19 {
-            return None
+    //Reads a MESH line, which has the same layout as the other shapes with the path of the mesh file after the name.
+    //The path may be followed by SMOOTH and a crease angle in radians to smooth the shading of the mesh.
+    pub fn read_mesh_from_tokens(tokens: &Vec<&str>) -> Result<Self, io::Error>{
+        if tokens.len() < 3 {
+            return Err(Error::new(ErrorKind::Other, format!("Could not read mesh from {}.", tokens.join(" "))));
         }
-
-        //Parse the tokens into f64s...
-        let token_slice: &[&str] = &tokens[2..];
-        let mut parsed_tokens: [f64;17] = [0.0;17];
-        for i in 0..token_slice.len(){
-            let parse_result = token_slice[i].to_string().trim().parse::<f64>();
-            match parse_result{
-                Err(_e) => return None,
-                Ok(num) => parsed_tokens[i] = num,
+        let mut shape_tokens = tokens.clone();
+        let mesh_file = shape_tokens.remove(2);
+        let mut crease_angle = None;
+        if shape_tokens.len() > 3 && shape_tokens[2] == "SMOOTH" {
+            match shape_tokens[3].to_string().trim().parse::<f64>() {
+                Ok(angle) if angle >= 0.0 => crease_angle = Some(angle),
+                _ => return Err(Error::new(ErrorKind::Other, format!("Could not read crease angle from {}.", tokens.join(" ")))),
             }
+            shape_tokens.drain(2..4);
         }
-
-        let pos = Vector4::point_from_slice(&parsed_tokens[0..3]);
-        let scale = Vector4::vec_from_slice(&parsed_tokens[3..6]);
-        let mut r_x: f64 = 0.0;
-        let mut r_y: f64 = 0.0;
-        let mut r_z: f64 = 0.0;
-        let mut color_start: usize = 6;
-        let mut lighting_param_start: usize = 9;
-
-        if tokens.len() > 16 {
-            r_x = parsed_tokens[6];
-            color_start += 1;
-            lighting_param_start += 1;
-        }
-        if tokens.len() > 17 {
-            r_y = parsed_tokens[7];
-            color_start += 1;
-            lighting_param_start += 1;
-        }
-        if tokens.len() > 18 {
-            r_z = parsed_tokens[8];
-            color_start += 1;
-            lighting_param_start += 1;
-        }
-        
-        let spatial_props = SpatialProps::new(pos, scale, r_x, r_y, r_z);
-
-        let color = Vector4::vec_from_slice(&parsed_tokens[color_start..lighting_param_start]);
-        let lighting_values = &parsed_tokens[lighting_param_start..];
-        let amb = lighting_values[0];
-        let diff = lighting_values[1];
-        let spec = lighting_values[2];
-        let refl = lighting_values[3];
-        let bright = lighting_values[4];
-
-        let lighting_props = LightingProps::new(color, amb, diff, spec, refl, bright);
-        return Some((spatial_props, lighting_props));
+        let (spatial_props, lighting_props) = match read_props_from_tokens(&shape_tokens) {
+            None => return Err(Error::new(ErrorKind::Other, format!("Could not read mesh from {}.", tokens.join(" ")))),
+            Some(props) => props,
+        };
+        let polygons = mesh_loader::load_mesh(mesh_file, &spatial_props, crease_angle)?;
+        return Ok(Self::new(spatial_props, lighting_props, polygons));
     }
 }
 impl Shape for MeshShape{
     fn lighting_props(&self) -> &LightingProps{
         return &self.lighting_props;
     }
-    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<(f64,Vector4,Vector4)>{
-        let mut min_t = max;
-        let mut col_data: Option<(f64,Vector4,Vector4)> = None; 
-        for polygon in self.polygons.iter(){
-            match polygon.check_collision(origin, ray, min, min_t, &self.spatial_props.inv_matrix) {
-                Some((t,col_pt,normal)) => {
-                    min_t = t;
-                    col_data = Some((t, col_pt, normal));
-                }
-                None => {}
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.spatial_props.bounding_box(&self.bvh.bounds());
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+
+        let mut crossings = Vec::<(f64, Vector4)>::new();
+        self.bvh.any_collision(&origin_prime, &ray_prime, f64::NEG_INFINITY, f64::INFINITY, |index, min, max| {
+            let polygon = &self.polygons[index];
+            if let Some(t) = polygon.check_collision(&origin_prime, &ray_prime, min, max){
+                crossings.push((t, polygon.normal_prime()));
             }
-        }
-        return col_data;
+            return false;
+        });
+        return intervals_from_crossings(crossings, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        //The ray is moved into object space once, rather than once for every polygon.
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+
+        let (t, index) = self.bvh.closest_collision(&origin_prime, &ray_prime, min, max, |index, min, max| {
+            return self.polygons[index].check_collision(&origin_prime, &ray_prime, min, max).map(|t| (t, index));
+        })?;
+
+        let polygon = &self.polygons[index];
+        let mut col_pt_prime = ray_prime.clone();
+        col_pt_prime *= t;
+        col_pt_prime += &origin_prime;
+        let normal = polygon.shading_normal_prime(&col_pt_prime, &self.spatial_props);
+
+        let mut col_pt = ray.clone();
+        col_pt *= t;
+        col_pt += origin;
+        col_pt.force_point();
+        let mut hit = Hit::new(t, col_pt, normal, polygon.normal_prime().dot(ray) < 0.0);
+        let (uv, tangent_prime) = polygon.surface_coords(&col_pt_prime);
+        hit.set_uv(uv, &tangent_prime, &self.spatial_props);
+        hit.face_id = index;
+        hit.color = polygon.color_at(&col_pt_prime);
+        return Some(hit);
     }
-}
\ No newline at end of file
+}
+impl fmt::Display for MeshShape{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Mesh of {} polygons with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.polygons.len(), self.spatial_props.scale, self.spatial_props.r_x, self.spatial_props.r_y, self.spatial_props.r_z, self.lighting_props.color, self.spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
@@ -0,0 +1,203 @@
+use std::fmt;
+use crate::matrix::Vector4;
+
+//The number of layers of noise added together for the noise, marble and wood patterns, each with twice the detail of the last.
+const NOISE_OCTAVES : u32 = 5;
+//How far the noise bends the veins of marble and the rings of wood.
+const MARBLE_TURBULENCE : f64 = 2.0;
+const WOOD_TURBULENCE : f64 = 0.3;
+//Moves points on the boundaries between the cells of checkers and stripes off of them,
+//so that faces lying exactly on a boundary do not flicker between both colours.
+const CELL_OFFSET : f64 = 0.000001;
+
+//The ways in which a pattern can vary across space.
+#[derive(Copy, Clone, PartialEq)]
+pub enum PatternKind{
+    //Cubes of alternating values.
+    Checker,
+    //Slabs of alternating values across the x axis.
+    Stripes,
+    //A ramp from the low value to the high value across each unit of the x axis.
+    Gradient,
+    //Fractal Perlin noise.
+    Noise,
+    //Veins across the x axis bent by noise.
+    Marble,
+    //Rings around the y axis bent by noise.
+    Wood,
+}
+
+impl PatternKind{
+    pub fn from_token(token: &str) -> Option<Self>{
+        return match token {
+            "CHECKER" => Some(Self::Checker),
+            "STRIPES" => Some(Self::Stripes),
+            "GRADIENT" => Some(Self::Gradient),
+            "NOISE" => Some(Self::Noise),
+            "MARBLE" => Some(Self::Marble),
+            "WOOD" => Some(Self::Wood),
+            &_ => None,
+        };
+    }
+}
+
+impl fmt::Display for PatternKind{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Checker => "Checker",
+            Self::Stripes => "Stripes",
+            Self::Gradient => "Gradient",
+            Self::Noise => "Noise",
+            Self::Marble => "Marble",
+            Self::Wood => "Wood",
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+//A procedural pattern, which is evaluated in the object space of the shape it is on.
+//It blends between a low and a high value, which are colours when the pattern drives the colour of a shape.
+#[derive(Copy, Clone)]
+pub struct Pattern{
+    pub kind: PatternKind,
+    //How many cells, stripes or ramps there are per unit of object space, which also sets the size of the noise.
+    pub scale: f64,
+    pub low: Vector4,
+    pub high: Vector4,
+}
+
+impl Pattern{
+    //How far the pattern is between its low and high values at a point, from 0 to 1.
+    pub fn amount_at(&self, point: &Vector4) -> f64{
+        let (x, y, z) = (point.x() * self.scale, point.y() * self.scale, point.z() * self.scale);
+        return match self.kind {
+            PatternKind::Checker => {
+                let cell = (x + CELL_OFFSET).floor() + (y + CELL_OFFSET).floor() + (z + CELL_OFFSET).floor();
+                cell.rem_euclid(2.0)
+            },
+            PatternKind::Stripes => (x + CELL_OFFSET).floor().rem_euclid(2.0),
+            PatternKind::Gradient => x.rem_euclid(1.0),
+            PatternKind::Noise => (0.5 + 0.5 * fractal_noise(x, y, z)).clamp(0.0, 1.0),
+            PatternKind::Marble => 0.5 + 0.5 * (std::f64::consts::PI * (x + MARBLE_TURBULENCE * turbulence(x, y, z))).sin(),
+            PatternKind::Wood => ((x * x + z * z).sqrt() + WOOD_TURBULENCE * fractal_noise(x, y, z)).rem_euclid(1.0),
+        };
+    }
+    //The value of the pattern at a point, blended between the low and high values.
+    pub fn value_at(&self, point: &Vector4) -> Vector4{
+        let amount = self.amount_at(point);
+        let mut value = self.low.clone();
+        value *= 1.0 - amount;
+        let mut high = self.high.clone();
+        high *= amount;
+        value += &high;
+        return value;
+    }
+}
+
+//Scrambles the corner of a cell of the noise lattice into one of the twelve directions from the middle of a cube to the middle of its edges.
+fn gradient(x: i64, y: i64, z: i64) -> (f64, f64, f64){
+    let mut hash = (x as u64).wrapping_mul(0x9E3779B97F4A7C15) ^ (y as u64).wrapping_mul(0xC2B2AE3D27D4EB4F) ^ (z as u64).wrapping_mul(0x165667B19E3779F9);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(0xBF58476D1CE4E5B9);
+    hash ^= hash >> 32;
+    return match hash % 12 {
+        0 => (1.0, 1.0, 0.0),
+        1 => (-1.0, 1.0, 0.0),
+        2 => (1.0, -1.0, 0.0),
+        3 => (-1.0, -1.0, 0.0),
+        4 => (1.0, 0.0, 1.0),
+        5 => (-1.0, 0.0, 1.0),
+        6 => (1.0, 0.0, -1.0),
+        7 => (-1.0, 0.0, -1.0),
+        8 => (0.0, 1.0, 1.0),
+        9 => (0.0, -1.0, 1.0),
+        10 => (0.0, 1.0, -1.0),
+        _ => (0.0, -1.0, -1.0),
+    };
+}
+
+//Eases from 0 to 1 so that the noise has no creases along the edges of the lattice.
+fn fade(t: f64) -> f64{
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64{
+    return a + (b - a) * t;
+}
+
+//Perlin's gradient noise, which varies smoothly between about -1 and 1 and is 0 at every whole numbered point.
+pub fn noise(x: f64, y: f64, z: f64) -> f64{
+    let (cell_x, cell_y, cell_z) = (x.floor(), y.floor(), z.floor());
+    let (fx, fy, fz) = (x - cell_x, y - cell_y, z - cell_z);
+    let (cell_x, cell_y, cell_z) = (cell_x as i64, cell_y as i64, cell_z as i64);
+    //The influence of the gradient at each corner of the cell.
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        let (gx, gy, gz) = gradient(cell_x + dx, cell_y + dy, cell_z + dz);
+        return gx * (fx - dx as f64) + gy * (fy - dy as f64) + gz * (fz - dz as f64);
+    };
+    let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+    let near = lerp(lerp(corner(0, 0, 0), corner(1, 0, 0), u), lerp(corner(0, 1, 0), corner(1, 1, 0), u), v);
+    let far = lerp(lerp(corner(0, 0, 1), corner(1, 0, 1), u), lerp(corner(0, 1, 1), corner(1, 1, 1), u), v);
+    return lerp(near, far, w);
+}
+
+//Layers of noise with ever finer detail and smaller size added together.
+pub fn fractal_noise(x: f64, y: f64, z: f64) -> f64{
+    let (mut sum, mut size, mut detail) = (0.0, 1.0, 1.0);
+    for _octave in 0..NOISE_OCTAVES{
+        sum += size * noise(x * detail, y * detail, z * detail);
+        size *= 0.5;
+        detail *= 2.0;
+    }
+    return sum;
+}
+
+//Like fractal_noise, but with every layer folded to be positive, which gives sharp creases where the noise crosses 0.
+pub fn turbulence(x: f64, y: f64, z: f64) -> f64{
+    let (mut sum, mut size, mut detail) = (0.0, 1.0, 1.0);
+    for _octave in 0..NOISE_OCTAVES{
+        sum += size * noise(x * detail, y * detail, z * detail).abs();
+        size *= 0.5;
+        detail *= 2.0;
+    }
+    return sum;
+}
+
+//The property of a surface that a pattern drives.
+#[derive(Copy, Clone, PartialEq)]
+pub enum PatternTarget{
+    Color,
+    Amb,
+    Diff,
+    Spec,
+    Refl,
+    Bright,
+}
+
+impl PatternTarget{
+    pub fn from_token(token: &str) -> Option<Self>{
+        return match token {
+            "COLOR" => Some(Self::Color),
+            "AMB" => Some(Self::Amb),
+            "DIFF" => Some(Self::Diff),
+            "SPEC" => Some(Self::Spec),
+            "REFL" => Some(Self::Refl),
+            "BRIGHT" => Some(Self::Bright),
+            &_ => None,
+        };
+    }
+}
+
+impl fmt::Display for PatternTarget{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Color => "color",
+            Self::Amb => "amb",
+            Self::Diff => "diff",
+            Self::Spec => "spec",
+            Self::Refl => "refl",
+            Self::Bright => "bright",
+        };
+        return write!(f, "{}", name);
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+use crate::matrix::Vector4;
+
+//How much light a non-metallic surface reflects when seen straight on, which is about the same for most plastics, stone and wood.
+const DIELECTRIC_REFLECTANCE : f64 = 0.04;
+//The smoothest a surface is treated as, since perfectly smooth surfaces would only reflect point lights in a single direction.
+const MIN_ROUGHNESS : f64 = 0.02;
+
+//The ways of working out how a surface reflects the light that reaches it.
+#[derive(Copy, Clone, PartialEq)]
+pub enum ShadingModel{
+    //The Phong model, driven by the amb, diff, spec, refl and bright coefficients.
+    Phong,
+    //A physically based model that uses the GGX microfacet distribution with the Cook-Torrance specular term.
+    //Both values run from 0 to 1. Rough surfaces spread their highlights and reflections out, while metallic surfaces
+    //tint them with their colour and have no diffuse light.
+    Pbr{roughness: f64, metallic: f64},
+}
+
+impl fmt::Display for ShadingModel{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Self::Phong => write!(f, "Phong shading"),
+            Self::Pbr{roughness, metallic} => write!(f, "Physically based shading with roughness {} and metallic {}", roughness, metallic),
+        };
+    }
+}
+
+//How much of each colour the surface reflects when seen straight on. Metals reflect their own colour while other surfaces reflect a little white.
+fn base_reflectance(base_color: &Vector4, metallic: f64) -> Vector4{
+    let mut reflectance = base_color.clone();
+    reflectance *= metallic;
+    let dielectric = DIELECTRIC_REFLECTANCE * (1.0 - metallic);
+    reflectance += &Vector4::vec(dielectric, dielectric, dielectric);
+    return reflectance;
+}
+
+//Schlick's approximation of the Fresnel equations, which raises the reflectance towards 1 as the light grazes the surface.
+fn fresnel(base_reflectance: &Vector4, cos: f64) -> Vector4{
+    let grazing = (1.0 - cos.clamp(0.0, 1.0)).powi(5);
+    let mut reflectance = Vector4::vec(1.0 - base_reflectance.x(), 1.0 - base_reflectance.y(), 1.0 - base_reflectance.z());
+    reflectance *= grazing;
+    reflectance += base_reflectance;
+    return reflectance;
+}
+
+//The GGX distribution, giving how many of the tiny facets of the surface face halfway between the light and the eye.
+fn distribution(normal_dot_half: f64, alpha: f64) -> f64{
+    let alpha_sq = alpha * alpha;
+    let denom = normal_dot_half * normal_dot_half * (alpha_sq - 1.0) + 1.0;
+    return alpha_sq / (std::f64::consts::PI * denom * denom);
+}
+
+//Smith's shadowing term with Schlick's approximation for GGX, giving how many facets are neither hidden from the light nor from the eye.
+fn geometry(normal_dot_view: f64, normal_dot_light: f64, roughness: f64) -> f64{
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let view = normal_dot_view / (normal_dot_view * (1.0 - k) + k);
+    let light = normal_dot_light / (normal_dot_light * (1.0 - k) + k);
+    return view * light;
+}
+
+//The share of the light of each colour arriving from a light that the surface sends towards the eye. The normal, direction to the light and
+//direction to the eye must be of unit length. Lights are taken to be as bright as what a white, rough, non-metallic surface facing them shows,
+//which keeps their colours comparable to those of the Phong model.
+pub fn reflected_light(base_color: &Vector4, roughness: f64, metallic: f64, normal: &Vector4, to_light: &Vector4, to_eye: &Vector4) -> Vector4{
+    let normal_dot_light = normal.dot(to_light);
+    let normal_dot_view = normal.dot(to_eye).max(0.0001);
+    if normal_dot_light <= 0.0 {
+        return Vector4::zero();
+    }
+    let mut half = to_light + to_eye;
+    if !(half.len_sq() > 0.0) {
+        return Vector4::zero();
+    }
+    half.normalize();
+
+    let roughness = roughness.clamp(MIN_ROUGHNESS, 1.0);
+    let specular_share = fresnel(&base_reflectance(base_color, metallic), half.dot(to_eye));
+    let specular = distribution(normal.dot(&half).max(0.0), roughness * roughness) * geometry(normal_dot_view, normal_dot_light, roughness)
+        / (4.0 * normal_dot_view * normal_dot_light);
+
+    //The light that is not reflected off the facets enters the surface and is scattered out evenly, unless the surface is a metal.
+    let mut diffuse = Vector4::vec(1.0 - specular_share.x(), 1.0 - specular_share.y(), 1.0 - specular_share.z());
+    diffuse *= base_color;
+    diffuse *= 1.0 - metallic;
+
+    let mut light = specular_share;
+    light *= specular * std::f64::consts::PI;
+    light += &diffuse;
+    light *= normal_dot_light;
+    return light;
+}
+
+//The share of the light of each colour arriving from the mirror direction that the surface sends towards the eye.
+//Rough surfaces scatter most of it away, so only smooth surfaces show clear reflections.
+pub fn mirror_reflectance(base_color: &Vector4, roughness: f64, metallic: f64, normal: &Vector4, to_eye: &Vector4) -> Vector4{
+    let mut reflectance = fresnel(&base_reflectance(base_color, metallic), normal.dot(to_eye));
+    reflectance *= (1.0 - roughness.clamp(0.0, 1.0)).powi(2);
+    return reflectance;
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 use crate::matrix::{Vector4,Matrix4};
+use crate::bounding_box::BoundingBox;
 
 pub struct SpatialProps {
     pub pos: Vector4,
@@ -7,6 +8,7 @@ pub struct SpatialProps {
     pub r_x: f64, 
     pub r_y: f64,
     pub r_z: f64,
+    pub matrix: Matrix4,
     pub inv_matrix: Matrix4,
     pub inv_transp: Matrix4,
 }
@@ -19,38 +21,124 @@ impl SpatialProps{
         let rot_z_matrix = Matrix4::rot_z(r_z);
 
         let rotation_matrix = &rot_z_matrix * &(&rot_y_matrix * &rot_x_matrix);
-        let inv_matrix = (&trans_matrix * &(&rotation_matrix * &scale_matrix)).inverse();
+        let matrix = &trans_matrix * &(&rotation_matrix * &scale_matrix);
+        let inv_matrix = matrix.inverse();
         let inv_transp = inv_matrix.transpose();
-        return Self{pos, scale, r_x, r_y, r_z, inv_matrix, inv_transp};
+        return Self{pos, scale, r_x, r_y, r_z, matrix, inv_matrix, inv_transp};
+    }
+    //Computes the world space box containing a box given in object space.
+    pub fn bounding_box(&self, object_bounds: &BoundingBox) -> BoundingBox{
+        return object_bounds.transform(&self.matrix);
     }
 }
-//A convex planar polygon in 3D space, used to construct more complex 3 Dimensional shapes.
+//A planar polygon in 3D space, used to construct more complex 3 Dimensional shapes.
+//The polygon may be concave and may have holes cut out of it.
 pub struct Polygon{
     points: Vec<Vector4>,
+    //The outlines of the holes, which lie in the same plane as the polygon.
+    holes: Vec<Vec<Vector4>>,
+    //Convex polygons without holes are checked against the projections onto each edge's inverse axis,
+    //which are left empty for the other polygons.
+    convex: bool,
     inverse_axes: Vec<Vector4>,
     min_max_projections: Vec<(f64,f64)>,
+    //The two coordinates kept when the polygon is flattened for the inside test of non-convex polygons.
+    //The coordinate along which the normal is largest is dropped.
+    flat_axes: (usize, usize),
     normal: Vector4,
     normal_prime: Vector4,
+    //Object space normals at each corner of a triangle, which are interpolated across it for smooth shading.
+    vertex_normals: Option<Vec<Vector4>>,
+    //Texture coordinates at each corner of a triangle, which are interpolated across it in the same way.
+    vertex_uvs: Option<Vec<(f64, f64)>>,
+    //Colours at each corner of a triangle, which are also interpolated across it.
+    vertex_colors: Option<Vec<Vector4>>,
 }
 
 impl Polygon{
-    pub fn new(mut points: Vec<Vector4>, spatial_props : &SpatialProps) -> Self{
-        let mut inverse_axes = Vec::<Vector4>::with_capacity(points.len());
-        let mut min_max_projections = Vec::<(f64,f64)>::with_capacity(points.len());
-        
-        let mut ab = points[1].clone();
-        ab -= &points[0];
-        let mut bc = points[2].clone();
-        bc -= &points[1];
-
-        let mut normal = ab.cross(&bc);
+    pub fn new(points: Vec<Vector4>, spatial_props : &SpatialProps) -> Self{
+        let mut normal = Self::winding_normal(&points);
         let mut a = points[0].clone();
         a.force_vec();
         if normal.dot(&a) < 0.0 {
             normal *= -1.0;
         }
-
+        return Self::with_normal(points, normal, spatial_props);
+    }
+    //Creates a polygon whose normal follows the counter-clockwise winding order of its points,
+    //rather than pointing away from the origin. Used for meshes that are not centered on the origin.
+    pub fn from_winding(points: Vec<Vector4>, spatial_props : &SpatialProps) -> Self{
+        let normal = Self::winding_normal(&points);
+        return Self::with_normal(points, normal, spatial_props);
+    }
+    //Creates a polygon with holes, whose normal follows the counter-clockwise winding order of its outline.
+    //The holes may be wound either way.
+    pub fn with_holes(points: Vec<Vector4>, holes: Vec<Vec<Vector4>>, spatial_props : &SpatialProps) -> Self{
+        let mut polygon = Self::from_winding(points, spatial_props);
+        if !holes.is_empty() {
+            polygon.holes = holes;
+            polygon.convex = false;
+            polygon.inverse_axes.clear();
+            polygon.min_max_projections.clear();
+        }
+        return polygon;
+    }
+    //The normal of a polygon whose points are listed counter-clockwise around it, with a length of twice its area.
+    //Polygons with more than three points use Newell's method, which also works when the first corner is concave.
+    pub fn winding_normal(points: &[Vector4]) -> Vector4{
+        if points.len() == 3 {
+            let mut ab = points[1].clone();
+            ab -= &points[0];
+            let mut bc = points[2].clone();
+            bc -= &points[1];
+            return ab.cross(&bc);
+        }
+        let mut normal = Vector4::zero();
+        for i in 0..points.len(){
+            let (a, b) = (&points[i], &points[(i + 1) % points.len()]);
+            normal.arr[0] += (a.y() - b.y()) * (a.z() + b.z());
+            normal.arr[1] += (a.z() - b.z()) * (a.x() + b.x());
+            normal.arr[2] += (a.x() - b.x()) * (a.y() + b.y());
+        }
+        return normal;
+    }
+    //Whether every corner of the polygon turns the same way around the normal.
+    fn is_convex(points: &[Vector4], normal: &Vector4) -> bool{
+        let (mut left, mut right) = (false, false);
+        for i in 0..points.len(){
+            let mut to_point = points[i].clone();
+            to_point -= &points[(i + points.len() - 1) % points.len()];
+            let mut to_next = points[(i + 1) % points.len()].clone();
+            to_next -= &points[i];
+            let turn = to_point.cross(&to_next).dot(normal);
+            if turn > 0.0 {
+                left = true;
+            }
+            else if turn < 0.0 {
+                right = true;
+            }
+        }
+        return !(left && right);
+    }
+    //The two coordinates that keep the most of a polygon's shape when it is flattened onto them.
+    fn flat_axes(normal: &Vector4) -> (usize, usize){
+        let abs = [normal.x().abs(), normal.y().abs(), normal.z().abs()];
+        return match (abs[0] >= abs[1] && abs[0] >= abs[2], abs[1] >= abs[2]) {
+            (true, _) => (1, 2),
+            (false, true) => (2, 0),
+            (false, false) => (0, 1),
+        };
+    }
+    fn with_normal(points: Vec<Vector4>, normal: Vector4, spatial_props : &SpatialProps) -> Self{
         let normal_prime = normal.apply_inv_transpose(&spatial_props.inv_transp); 
+        let flat_axes = Self::flat_axes(&normal);
+        let convex = Self::is_convex(&points, &normal);
+        if !convex {
+            return Self{points, holes: Vec::new(), convex, inverse_axes: Vec::new(), min_max_projections: Vec::new(), flat_axes, normal, normal_prime, vertex_normals: None, vertex_uvs: None, vertex_colors: None};
+        }
+
+        let mut inverse_axes = Vec::<Vector4>::with_capacity(points.len());
+        let mut min_max_projections = Vec::<(f64,f64)>::with_capacity(points.len());
         for i in 0..points.len(){
             let mut vec = points[i];
             let prev_point = match i {
@@ -79,12 +167,194 @@ impl Polygon{
             min_max_projections.push((min, max));
         }
        
-        return Self{points, inverse_axes, min_max_projections, normal, normal_prime};
+        return Self{points, holes: Vec::new(), convex, inverse_axes, min_max_projections, flat_axes, normal, normal_prime, vertex_normals: None, vertex_uvs: None, vertex_colors: None};
+    }
+    //Splits a planar polygon, which may be concave, into triangles by repeatedly cutting off corners that
+    //have no other points inside them. The triangles are given as indices into the points and keep their winding.
+    //Convex polygons are split into a fan around the first point.
+    pub fn triangulate(points: &[Vector4]) -> Vec<[usize;3]>{
+        if points.len() < 3 {
+            return Vec::new();
+        }
+        if points.len() == 3 {
+            return vec![[0, 1, 2]];
+        }
+        let normal = Self::winding_normal(points);
+        let (u, v) = Self::flat_axes(&normal);
+        //The flattened axes are kept in cyclic order, so the flattened polygon winds counter-clockwise
+        //when the dropped coordinate of the normal is positive and clockwise otherwise.
+        let sign = normal.arr[3 - u - v].signum();
+        let turn = |a: usize, b: usize, c: usize| -> f64 {
+            let (pa, pb, pc) = (&points[a], &points[b], &points[c]);
+            return sign * ((pb.arr[u] - pa.arr[u]) * (pc.arr[v] - pb.arr[v]) - (pb.arr[v] - pa.arr[v]) * (pc.arr[u] - pb.arr[u]));
+        };
+
+        let mut remaining: Vec<usize> = (0..points.len()).collect();
+        let mut triangles = Vec::<[usize;3]>::with_capacity(points.len() - 2);
+        let mut k = 1;
+        let mut failures = 0;
+        while remaining.len() > 3 {
+            let len = remaining.len();
+            let (prev, corner, next) = (remaining[(k + len - 1) % len], remaining[k], remaining[(k + 1) % len]);
+            let is_ear = turn(prev, corner, next) > 0.0 && !remaining.iter().any(|other| {
+                if [prev, corner, next].iter().any(|index| points[*index].arr == points[*other].arr) {
+                    return false;
+                }
+                return turn(prev, corner, *other) >= 0.0 && turn(corner, next, *other) >= 0.0 && turn(next, prev, *other) >= 0.0;
+            });
+            if is_ear {
+                triangles.push([prev, corner, next]);
+                remaining.remove(k);
+                k = k.min(remaining.len() - 1).max(1);
+                failures = 0;
+            }
+            else {
+                failures += 1;
+                //Polygons that cross over themselves may have no ears left, in which case the rest is split into a fan.
+                if failures >= len {
+                    break;
+                }
+                k = (k + 1) % len;
+            }
+        }
+        for i in 1..(remaining.len() - 1){
+            triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+        }
+        return triangles;
     }
-    pub fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max:f64, inv_matrix: &Matrix4) -> Option<(f64,Vector4,Vector4)>{
-        let origin_prime = inv_matrix * origin;
-        let ray_prime = inv_matrix * ray;
+    //The object space box containing the polygon.
+    pub fn bounding_box(&self) -> BoundingBox{
+        return BoundingBox::from_points(&self.points);
+    }
+    pub fn corner_count(&self) -> usize{
+        return self.points.len();
+    }
+    pub fn hole_count(&self) -> usize{
+        return self.holes.len();
+    }
+    //The world space normal of the polygon.
+    pub fn normal_prime(&self) -> Vector4{
+        return self.normal_prime;
+    }
+    //Gives a triangle a normal at each of its corners. Other polygons are always shaded flat.
+    pub fn set_vertex_normals(&mut self, mut vertex_normals: Vec<Vector4>){
+        if self.points.len() != 3 || vertex_normals.len() != 3 {
+            return;
+        }
+        for normal in vertex_normals.iter_mut(){
+            normal.force_vec();
+            normal.normalize();
+        }
+        self.vertex_normals = Some(vertex_normals);
+    }
+    //Gives a triangle texture coordinates at each of its corners.
+    pub fn set_vertex_uvs(&mut self, vertex_uvs: Vec<(f64, f64)>){
+        if self.points.len() != 3 || vertex_uvs.len() != 3 {
+            return;
+        }
+        self.vertex_uvs = Some(vertex_uvs);
+    }
+    //Gives a triangle a colour at each of its corners.
+    pub fn set_vertex_colors(&mut self, vertex_colors: Vec<Vector4>){
+        if self.points.len() != 3 || vertex_colors.len() != 3 {
+            return;
+        }
+        self.vertex_colors = Some(vertex_colors);
+    }
+    //The barycentric coordinates of a point on a triangle, which weigh each corner by how close the point is to it.
+    fn barycentric(&self, col_pt_prime: &Vector4) -> [f64;3]{
+        let winding = Self::winding_normal(&self.points);
+        let area_sq = winding.len_sq();
+        let mut weights = [0.0;3];
+        for i in 0..2{
+            let mut edge = self.points[(i + 2) % 3].clone();
+            edge -= &self.points[(i + 1) % 3];
+            let mut to_point = col_pt_prime.clone();
+            to_point -= &self.points[(i + 1) % 3];
+            weights[i] = winding.dot(&edge.cross(&to_point)) / area_sq;
+        }
+        weights[2] = 1.0 - weights[0] - weights[1];
+        return weights;
+    }
+    //The texture coordinates of a point on the polygon, given in object space, with the object space direction in which u increases.
+    //Triangles with texture coordinates at their corners interpolate them. Other triangles use the barycentric
+    //weights of their second and third corners, and other polygons use their flattened coordinates.
+    pub fn surface_coords(&self, col_pt_prime: &Vector4) -> ((f64, f64), Vector4){
+        if self.points.len() != 3 {
+            let (u, v) = self.flat_axes;
+            let mut tangent = Vector4::zero();
+            tangent.arr[u] = 1.0;
+            let mut along_normal = self.normal.clone();
+            along_normal *= self.normal.arr[u] / self.normal.len_sq();
+            tangent -= &along_normal;
+            return ((col_pt_prime.arr[u], col_pt_prime.arr[v]), tangent);
+        }
+
+        let weights = self.barycentric(col_pt_prime);
+        let mut edge_b = self.points[1].clone();
+        edge_b -= &self.points[0];
+        let vertex_uvs = match &self.vertex_uvs {
+            None => return ((weights[1], weights[2]), edge_b),
+            Some(vertex_uvs) => vertex_uvs,
+        };
+        let mut uv = (0.0, 0.0);
+        for (vertex_uv, weight) in vertex_uvs.iter().zip(weights.iter()){
+            uv.0 += vertex_uv.0 * weight;
+            uv.1 += vertex_uv.1 * weight;
+        }
+
+        //Solves for the direction along the triangle in which u increases while v stays the same.
+        let mut edge_c = self.points[2].clone();
+        edge_c -= &self.points[0];
+        let (du_b, dv_b) = (vertex_uvs[1].0 - vertex_uvs[0].0, vertex_uvs[1].1 - vertex_uvs[0].1);
+        let (du_c, dv_c) = (vertex_uvs[2].0 - vertex_uvs[0].0, vertex_uvs[2].1 - vertex_uvs[0].1);
+        let det = du_b * dv_c - du_c * dv_b;
+        if det == 0.0 {
+            return (uv, edge_b);
+        }
+        edge_b *= dv_c / det;
+        edge_c *= dv_b / det;
+        edge_b -= &edge_c;
+        return (uv, edge_b);
+    }
+    //The colour at a point on the polygon, given in object space, found by interpolating the vertex colours
+    //using the barycentric coordinates of the point. Polygons without vertex colours have none.
+    pub fn color_at(&self, col_pt_prime: &Vector4) -> Option<Vector4>{
+        let vertex_colors = self.vertex_colors.as_ref()?;
+        let weights = self.barycentric(col_pt_prime);
+
+        let mut color = Vector4::zero();
+        for (vertex_color, weight) in vertex_colors.iter().zip(weights.iter()){
+            let mut weighted = vertex_color.clone();
+            weighted *= *weight;
+            color += &weighted;
+        }
+        return Some(color);
+    }
+    //The world space normal used for shading at a point on the polygon, given in object space.
+    //Without vertex normals this is the normal of the polygon, otherwise the vertex normals are interpolated
+    //using the barycentric coordinates of the point.
+    pub fn shading_normal_prime(&self, col_pt_prime: &Vector4, spatial_props: &SpatialProps) -> Vector4{
+        let vertex_normals = match &self.vertex_normals {
+            None => return self.normal_prime,
+            Some(vertex_normals) => vertex_normals,
+        };
+        let weights = self.barycentric(col_pt_prime);
 
+        let mut normal = Vector4::zero();
+        for (vertex_normal, weight) in vertex_normals.iter().zip(weights.iter()){
+            let mut weighted = vertex_normal.clone();
+            weighted *= *weight;
+            normal += &weighted;
+        }
+        if normal.len_sq() <= 0.0 {
+            return self.normal_prime;
+        }
+        return normal.apply_inv_transpose(&spatial_props.inv_transp);
+    }
+    //Finds the t at which a ray collides with the polygon.
+    //The ray must already be transformed into the object space of the shape the polygon belongs to.
+    pub fn check_collision(&self, origin_prime: &Vector4, ray_prime: &Vector4, min: f64, max:f64) -> Option<f64>{
         let ray_proj = ray_prime.dot(&self.normal);
         let origin_proj = origin_prime.dot(&self.normal);
         
@@ -92,26 +362,51 @@ impl Polygon{
         let distance = surface_proj - origin_proj;
         //let distance = 1.0 - origin_proj;
         let t = distance / ray_proj;
-        if t <= min || t >= max{
+        //Written so that the NaN produced by a ray lying in the plane of the polygon is rejected.
+        if !(t > min && t < max){
             return None;
         }
             
-        let mut col_pt_prime = ray_prime;
+        let mut col_pt_prime = ray_prime.clone();
         col_pt_prime *= t;
-        col_pt_prime += &origin_prime;
+        col_pt_prime += origin_prime;
 
+        return match self.contains(&col_pt_prime) {
+            true => Some(t),
+            false => None,
+        };
+    }
+    //Checks whether a point in the plane of the polygon is inside of it.
+    pub fn contains(&self, point: &Vector4) -> bool{
+        if !self.convex {
+            return self.contains_flat(point);
+        }
         for (inv_axis,(p_min, p_max)) in self.inverse_axes.iter().zip(self.min_max_projections.iter()){
-            let dot = col_pt_prime.dot(inv_axis);
+            let dot = point.dot(inv_axis);
             if dot < *p_min || dot > *p_max {
-                return None;
+                return false;
             }
         }
-
-        let mut col_pt = ray.clone();
-        col_pt *= t;
-        col_pt += &origin;
-        col_pt.force_point(); 
-        return Some((t, col_pt, self.normal_prime));
+        return true;
+    }
+    //Checks whether a point in the plane of the polygon is inside of it by counting how many edges of the outline
+    //and holes a line from the point crosses, once the polygon is flattened. An odd count means that the point is inside.
+    fn contains_flat(&self, point: &Vector4) -> bool{
+        let (u, v) = self.flat_axes;
+        let (pu, pv) = (point.arr[u], point.arr[v]);
+        let mut inside = false;
+        for ring in std::iter::once(&self.points).chain(self.holes.iter()){
+            for i in 0..ring.len(){
+                let (a, b) = (&ring[i], &ring[(i + 1) % ring.len()]);
+                if (a.arr[v] > pv) != (b.arr[v] > pv) {
+                    let cross_u = a.arr[u] + (pv - a.arr[v]) * (b.arr[u] - a.arr[u]) / (b.arr[v] - a.arr[v]);
+                    if pu < cross_u {
+                        inside = !inside;
+                    }
+                }
+            }
+        }
+        return inside;
     }
 }
 impl fmt::Display for Polygon{
@@ -121,6 +416,12 @@ impl fmt::Display for Polygon{
         for pt in self.points.iter(){
             write!(f, "{}\n", pt)?;
         }
+        for hole in self.holes.iter(){
+            write!(f, "Hole:\n")?;
+            for pt in hole.iter(){
+                write!(f, "{}\n", pt)?;
+            }
+        }
         return write!(f, "\n");
     }
 }
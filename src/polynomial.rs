@@ -0,0 +1,151 @@
+//Real root finding for the low degree polynomials that come up when intersecting rays with curved shapes.
+//Coefficients are always given from the highest power down, so [a, b, c] is a*x^2 + b*x + c.
+
+const MAX_ITERATIONS : usize = 100;
+
+pub fn evaluate(coeffs: &[f64], x: f64) -> f64{
+    let mut result = 0.0;
+    for coeff in coeffs.iter(){
+        result = result * x + coeff;
+    }
+    return result;
+}
+
+fn derivative(coeffs: &[f64]) -> Vec<f64>{
+    let degree = coeffs.len() - 1;
+    let mut result = Vec::<f64>::with_capacity(degree);
+    for i in 0..degree{
+        result.push(coeffs[i] * (degree - i) as f64);
+    }
+    return result;
+}
+
+//Finds the real roots of the polynomial between lo and hi, in increasing order.
+//The roots of the derivative split the range into pieces on which the polynomial is monotonic,
+//so each piece whose ends have opposite signs holds exactly one root. This avoids the loss of
+//precision that closed form solutions for cubics and quartics suffer from.
+//Roots where the polynomial only touches zero without changing sign are only reported if it comes out as exactly zero there.
+pub fn roots_in(coeffs: &[f64], lo: f64, hi: f64) -> Vec<f64>{
+    let first_nonzero = coeffs.iter().position(|coeff| *coeff != 0.0);
+    let coeffs = match first_nonzero {
+        None => return Vec::new(),
+        Some(start) => &coeffs[start..],
+    };
+    if coeffs.len() < 2 || !(lo < hi) {
+        return Vec::new();
+    }
+    if coeffs.len() == 2 {
+        let root = -coeffs[1] / coeffs[0];
+        return match root >= lo && root <= hi {
+            true => vec![root],
+            false => Vec::new(),
+        };
+    }
+
+    let slope = derivative(coeffs);
+    let mut bounds = Vec::<f64>::with_capacity(coeffs.len() + 1);
+    bounds.push(lo);
+    bounds.extend(roots_in(&slope, lo, hi));
+    bounds.push(hi);
+
+    let mut roots = Vec::<f64>::with_capacity(coeffs.len() - 1);
+    for pair in bounds.windows(2){
+        let (a, b) = (pair[0], pair[1]);
+        let (f_a, f_b) = (evaluate(coeffs, a), evaluate(coeffs, b));
+        if f_a == 0.0 {
+            if roots.last() != Some(&a) {
+                roots.push(a);
+            }
+        }
+        else if f_b != 0.0 && (f_a < 0.0) != (f_b < 0.0) {
+            roots.push(refine_root(coeffs, &slope, a, b, f_a < 0.0));
+        }
+    }
+    if evaluate(coeffs, hi) == 0.0 && roots.last() != Some(&hi) {
+        roots.push(hi);
+    }
+    return roots;
+}
+
+//Narrows down the single root between a and b using Newton's method, falling back to bisection
+//whenever a Newton step would leave the bracket.
+fn refine_root(coeffs: &[f64], slope: &[f64], mut a: f64, mut b: f64, rising: bool) -> f64{
+    let mut x = 0.5 * (a + b);
+    for _i in 0..MAX_ITERATIONS{
+        let f_x = evaluate(coeffs, x);
+        if f_x == 0.0 {
+            return x;
+        }
+        if (f_x < 0.0) == rising {
+            a = x;
+        }
+        else {
+            b = x;
+        }
+        if b - a <= f64::EPSILON * x.abs().max(1.0) {
+            break;
+        }
+
+        let newton = x - f_x / evaluate(slope, x);
+        if (newton - x).abs() <= f64::EPSILON * x.abs().max(1.0) {
+            return newton;
+        }
+        x = match newton > a && newton < b {
+            true => newton,
+            false => 0.5 * (a + b),
+        };
+    }
+    return x;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(found: &[f64], expected: &[f64]){
+        assert_eq!(found.len(), expected.len(), "found {:?}, expected {:?}", found, expected);
+        for (found, expected) in found.iter().zip(expected.iter()){
+            assert!((found - expected).abs() < 0.000000001, "found {:?}, expected {:?}", found, expected);
+        }
+    }
+
+    #[test]
+    fn quartic_with_distinct_roots(){
+        //(x - 1)(x - 2)(x - 3)(x - 4)
+        let coeffs = [1.0, -10.0, 35.0, -50.0, 24.0];
+        assert_roots(&roots_in(&coeffs, 0.0, 5.0), &[1.0, 2.0, 3.0, 4.0]);
+        assert_roots(&roots_in(&coeffs, 1.5, 3.5), &[2.0, 3.0]);
+        //(x + 0.5)(x - 0.25)(x - 7)(x + 3), with a leading coefficient and roots of both signs.
+        let coeffs = [2.0, -7.5, -44.25, -9.5, 5.25];
+        assert_roots(&roots_in(&coeffs, -10.0, 10.0), &[-3.0, -0.5, 0.25, 7.0]);
+    }
+
+    #[test]
+    fn quartic_with_double_root(){
+        //(x - 1)^2 (x - 3)(x + 2). The double root only touches zero, so it is reported once if at all.
+        let coeffs = [1.0, -3.0, -3.0, 11.0, -6.0];
+        let roots = roots_in(&coeffs, -5.0, 5.0);
+        let simple: Vec<f64> = roots.iter().copied().filter(|root| (root - 1.0).abs() > 0.000001).collect();
+        assert_roots(&simple, &[-2.0, 3.0]);
+        assert!(roots.len() - simple.len() <= 1);
+        //(x - 1)^2 (x - 2)^2 never changes sign.
+        let coeffs = [1.0, -6.0, 13.0, -12.0, 4.0];
+        let roots = roots_in(&coeffs, 1.5, 5.0);
+        assert!(roots.len() <= 1 && roots.iter().all(|root| (root - 2.0).abs() < 0.000001));
+    }
+
+    #[test]
+    fn quartic_without_real_roots(){
+        //(x^2 + 1)(x^2 + 4)
+        assert!(roots_in(&[1.0, 0.0, 5.0, 0.0, 4.0], -100.0, 100.0).is_empty());
+        //(x - 1)(x - 2)(x^2 + 1) has its roots outside of the range.
+        assert!(roots_in(&[1.0, -3.0, 3.0, -3.0, 2.0], 2.5, 10.0).is_empty());
+    }
+
+    #[test]
+    fn leading_zeros_lower_the_degree(){
+        assert_roots(&roots_in(&[0.0, 0.0, 1.0, 0.0, -4.0], -5.0, 5.0), &[-2.0, 2.0]);
+        assert_roots(&roots_in(&[0.0, 0.0, 0.0, 2.0, -1.0], -5.0, 5.0), &[0.5]);
+        assert!(roots_in(&[0.0, 0.0, 0.0, 0.0, 0.0], -5.0, 5.0).is_empty());
+    }
+}
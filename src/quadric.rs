@@ -0,0 +1,215 @@
+use std::fmt;
+use crate::matrix::Vector4;
+use crate::shape::{LightingProps,Shape,Interval,Hit,read_props_from_tokens};
+use crate::polygon::SpatialProps;
+use crate::bounding_box::BoundingBox;
+
+//The surface where Ax^2 + By^2 + Cz^2 + Dxy + Exz + Fyz + Gx + Hy + Iz + J = 0 in object space,
+//which covers ellipsoids, paraboloids, hyperboloids, cones and cylinders among others.
+//Points where the left hand side is negative are inside the surface.
+//A clipped quadric only keeps the part of the surface inside the clipping box, leaving it open.
+pub struct Quadric{
+    pub spatial_props: SpatialProps,
+    pub lighting_props: LightingProps,
+    pub coeffs: [f64;10],
+    pub clip: Option<BoundingBox>,
+}
+
+impl Quadric{
+    //Reads a QUADRIC line, which has the ten coefficients after the name, optionally followed by CLIP
+    //and the minimum and maximum corners of the clipping box, and is otherwise laid out like a sphere.
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        if tokens.len() < 12 {
+            return None;
+        }
+        let mut coeffs = [0.0;10];
+        for i in 0..10{
+            coeffs[i] = tokens[i + 2].to_string().trim().parse::<f64>().ok()?;
+        }
+        //Without any squared or linear terms there is no surface.
+        if coeffs[..9].iter().all(|coeff| *coeff == 0.0) {
+            return None;
+        }
+
+        let mut shape_tokens = tokens.clone();
+        shape_tokens.drain(2..12);
+        let mut clip = None;
+        if shape_tokens.len() > 2 && shape_tokens[2] == "CLIP" {
+            if shape_tokens.len() < 9 {
+                return None;
+            }
+            let mut corners = [0.0;6];
+            for i in 0..6{
+                corners[i] = shape_tokens[i + 3].to_string().trim().parse::<f64>().ok()?;
+            }
+            if (0..3).any(|i| corners[i] >= corners[i + 3]) {
+                return None;
+            }
+            clip = Some(BoundingBox::new(Vector4::point_from_slice(&corners[0..3]), Vector4::point_from_slice(&corners[3..6])));
+            shape_tokens.drain(2..9);
+        }
+        return match read_props_from_tokens(&shape_tokens){
+            Some((spatial_props, lighting_props)) => Some(Self{spatial_props, lighting_props, coeffs, clip}),
+            None => None,
+        }
+    }
+
+    //Gives the a, b and c of a*t^2 + 2*b*t + c, the value of the quadric along origin_prime + t * ray_prime.
+    fn line_coeffs(&self, origin_prime: &Vector4, ray_prime: &Vector4) -> (f64, f64, f64){
+        let [a2, b2, c2, d, e, f, g, h, i, j] = self.coeffs;
+        let (ox, oy, oz) = (origin_prime.x(), origin_prime.y(), origin_prime.z());
+        let (rx, ry, rz) = (ray_prime.x(), ray_prime.y(), ray_prime.z());
+        let a = a2 * rx * rx + b2 * ry * ry + c2 * rz * rz + d * rx * ry + e * rx * rz + f * ry * rz;
+        let b = a2 * ox * rx + b2 * oy * ry + c2 * oz * rz
+            + 0.5 * (d * (ox * ry + oy * rx) + e * (ox * rz + oz * rx) + f * (oy * rz + oz * ry) + g * rx + h * ry + i * rz);
+        let c = a2 * ox * ox + b2 * oy * oy + c2 * oz * oz + d * ox * oy + e * ox * oz + f * oy * oz + g * ox + h * oy + i * oz + j;
+        return (a, b, c);
+    }
+
+    //Solves a*t^2 + 2*b*t + c = 0, giving the real roots in increasing order.
+    //The roots are found in a way that avoids cancellation when one of them is much smaller than the other.
+    fn roots(a: f64, b: f64, c: f64) -> Vec<f64>{
+        if a == 0.0 {
+            return match b == 0.0 {
+                true => Vec::new(),
+                false => vec![-c / (2.0 * b)],
+            };
+        }
+        let det = b * b - a * c;
+        if det < 0.0 {
+            return Vec::new();
+        }
+        let q = -(b + det.sqrt().copysign(b));
+        if q == 0.0 {
+            return vec![0.0];
+        }
+        let (first, second) = (q / a, c / q);
+        return match first <= second {
+            true => vec![first, second],
+            false => vec![second, first],
+        };
+    }
+
+    //The gradient of the quadric, which points out of the surface.
+    fn normal_at(&self, col_pt_prime: &Vector4) -> Vector4{
+        let [a2, b2, c2, d, e, f, g, h, i, _j] = self.coeffs;
+        let (x, y, z) = (col_pt_prime.x(), col_pt_prime.y(), col_pt_prime.z());
+        return Vector4::vec(
+            2.0 * a2 * x + d * y + e * z + g,
+            2.0 * b2 * y + d * x + f * z + h,
+            2.0 * c2 * z + e * x + f * y + i,
+        );
+    }
+
+    fn point_at(origin_prime: &Vector4, ray_prime: &Vector4, t: f64) -> Vector4{
+        let mut col_pt_prime = ray_prime.clone();
+        col_pt_prime *= t;
+        col_pt_prime += origin_prime;
+        col_pt_prime.force_point();
+        return col_pt_prime;
+    }
+
+    fn is_clipped(&self, col_pt_prime: &Vector4) -> bool{
+        return match &self.clip {
+            None => false,
+            Some(clip) => (0..3).any(|i| col_pt_prime.arr[i] < clip.min.arr[i] || col_pt_prime.arr[i] > clip.max.arr[i]),
+        };
+    }
+}
+
+impl Shape for Quadric{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return match &self.clip {
+            Some(clip) => self.spatial_props.bounding_box(clip),
+            None => BoundingBox::infinite(),
+        };
+    }
+    //Clipping leaves holes in the surface, so only unclipped quadrics enclose a volume.
+    fn is_solid(&self) -> bool{
+        return self.clip.is_none();
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+        let (a, b, c) = self.line_coeffs(&origin_prime, &ray_prime);
+
+        //The line is inside wherever a*t^2 + 2*b*t + c is negative, which may stretch to either end of the line.
+        //The ends at infinity are never hit, so they are given no normal.
+        let crossing = |t: f64| -> (f64, Vector4) {
+            let normal_prime = self.normal_at(&Self::point_at(&origin_prime, &ray_prime, t));
+            return (t, normal_prime.apply_inv_transpose(&self.spatial_props.inv_transp));
+        };
+        let before = (f64::NEG_INFINITY, Vector4::zero());
+        let after = (f64::INFINITY, Vector4::zero());
+        let roots = Self::roots(a, b, c);
+        return match roots.as_slice() {
+            [] => match a < 0.0 || (a == 0.0 && c < 0.0) {
+                true => vec![Interval{enter: before, exit: after}],
+                false => Vec::new(),
+            },
+            [t] if a == 0.0 => match b > 0.0 {
+                true => vec![Interval{enter: before, exit: crossing(*t)}],
+                false => vec![Interval{enter: crossing(*t), exit: after}],
+            },
+            //A line that only touches the surface stays on the side of it that the rest of the line is on.
+            [_t] => match a < 0.0 {
+                true => vec![Interval{enter: before, exit: after}],
+                false => Vec::new(),
+            },
+            [first, second, ..] => match a > 0.0 {
+                true => vec![Interval{enter: crossing(*first), exit: crossing(*second)}],
+                false => vec![Interval{enter: before, exit: crossing(*first)}, Interval{enter: crossing(*second), exit: after}],
+            },
+        };
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let ray_prime = &self.spatial_props.inv_matrix * ray;
+        let (a, b, c) = self.line_coeffs(&origin_prime, &ray_prime);
+
+        for t in Self::roots(a, b, c){
+            if !(t > min && t < max) {
+                continue;
+            }
+            let col_pt_prime = Self::point_at(&origin_prime, &ray_prime, t);
+            if self.is_clipped(&col_pt_prime) {
+                continue;
+            }
+            //The normal faces the ray, so the inside of an open surface is lit as well as the outside.
+            let mut normal_prime = self.normal_at(&col_pt_prime);
+            let front_face = normal_prime.dot(&ray_prime) <= 0.0;
+            if !front_face {
+                normal_prime *= -1.0;
+            }
+
+            let mut col_pt = ray.clone();
+            col_pt *= t;
+            col_pt += origin;
+            col_pt.force_point();
+            let normal = normal_prime.apply_inv_transpose(&self.spatial_props.inv_transp);
+            let mut hit = Hit::new(t, col_pt, normal, front_face);
+            let (uv, tangent_prime) = Hit::box_coords(&col_pt_prime, &normal_prime);
+            hit.set_uv(uv, &tangent_prime, &self.spatial_props);
+            return Some(hit);
+        }
+        return None;
+    }
+}
+
+impl fmt::Display for Quadric{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let coeffs: Vec<String> = self.coeffs.iter().map(|coeff| coeff.to_string()).collect();
+        write!(f, "Quadric with coefficients {}", coeffs.join(" "))?;
+        if let Some(clip) = &self.clip {
+            write!(f, " clipped to {} - {}", clip.min, clip.max)?;
+        }
+        write!(f, ", scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.spatial_props.scale, self.spatial_props.r_x, self.spatial_props.r_y, self.spatial_props.r_z, self.lighting_props.color, self.spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
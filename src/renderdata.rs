@@ -4,9 +4,26 @@ use std::io::{Error, ErrorKind};
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter};
 use std::path::Path;
+use std::collections::HashMap;
+use std::sync::Arc;
 use crate::matrix::Vector4;
-use crate::shape::{LightingProps,Shape};
-use crate::elements::{Cube, Sphere, Tetrahedron, Dodecahedron, Light};
+use crate::shape::{LightingProps,Surface,Shape,Hit,read_props_from_tokens,read_spatial_props_from_tokens};
+use crate::elements::{Cube, Sphere, Tetrahedron, Dodecahedron, Octahedron, Icosahedron, GeodesicSphere, ConvexPolyhedron, Plane, Quad, Disk, FlatPolygon, Cylinder, Cone, Light};
+use crate::mesh_shape::MeshShape;
+use crate::torus::Torus;
+use crate::quadric::Quadric;
+use crate::sweep::{Lathe,Extrusion};
+use crate::bezier::BezierSurface;
+use crate::material;
+use crate::material::MaterialShape;
+use crate::pbr;
+use crate::pbr::ShadingModel;
+use crate::csg::{Csg, CsgOperation};
+use crate::heightfield::Heightfield;
+use crate::sdf::Sdf;
+use crate::instance::{SharedObject, Instance};
+use crate::bounding_box::BoundingBox;
+use crate::bvh::Bvh;
 use image::codecs::pnm::{PnmEncoder, PnmSubtype, SampleEncoding};
 use image::codecs::png::PngEncoder;
 use image::{ImageEncoder, Rgb};
@@ -19,10 +36,8 @@ pub struct RenderData{
     top: f64,
     width: usize, 
     height: usize,
-    spheres: Vec<Sphere>,
-    tetras: Vec<Tetrahedron>,
-    dodas: Vec<Dodecahedron>,
-    cubes: Vec<Cube>,
+    shapes: Vec<Box<dyn Shape>>,
+    bvh: Bvh,
     lights: Vec<Light>,
     back_color: Vector4,
     amb_color: Vector4,
@@ -59,45 +74,51 @@ impl RenderData{
         Ok(())
     }
 
-    pub fn check_collisions(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<(&dyn Shape, Vector4,Vector4)> {
-        let mut lowest = max;
-        let mut col_data = None;
-        for sphere in self.spheres.iter(){
-            if let Some((t, col_pt, normal)) = sphere.check_collision(origin, ray, min, lowest){
-                col_data = Some((sphere as &dyn Shape, col_pt, normal));
-                lowest = t;
-            }
-        }
-        for cube in self.cubes.iter(){
-            if let Some((t, col_pt, normal)) = cube.check_collision(origin, ray, min, lowest){
-                col_data = Some((cube as &dyn Shape, col_pt, normal));
-                lowest = t;
-            }
-        }
-        for tetra in self.tetras.iter(){
-            if let Some((t, col_pt, normal)) = tetra.check_collision(origin, ray, min, lowest){
-                col_data = Some((tetra as &dyn Shape, col_pt, normal));
-                lowest = t;
-            }
-        }
-        for doda in self.dodas.iter(){
-            if let Some((t, col_pt, normal)) = doda.check_collision(origin, ray, min, lowest){
-                col_data = Some((doda as &dyn Shape, col_pt, normal));
-                lowest = t;
-            }
-        }
+    pub fn check_collisions(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<(&LightingProps, Hit)> {
+        let col_data = self.bvh.closest_collision(origin, ray, min, max, |index, min, max| {
+            return self.shapes[index].check_lit_collision(origin, ray, min, max).map(|(mut hit, lighting_props)| {
+                hit.add_shape_index(index, self.shapes.len());
+                return (hit.t, (lighting_props, hit));
+            });
+        });
 
         return match col_data {
             None => None,
-            Some((shape, col_pt, mut normal)) => {
-                normal.normalize();
-                Some((shape, col_pt, normal))
+            Some((_t, (lighting_props, mut hit))) => {
+                hit.normal.normalize();
+                Some((lighting_props, hit))
             }
         };
     }
+    //The share of the light that reaches origin + max * ray from the origin, which is what shadows need to know.
+    //Opaque shapes let no light through, while each transparent shape in the way lets through transp of it, so its shadow is
+    //only 1 - transp as dark. Every shape is counted once however many of its surfaces are crossed.
+    pub fn shadow_transmission(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> f64 {
+        let mut transmission = 1.0;
+        let mut crossed = Vec::<usize>::new();
+        let blocked = self.bvh.any_collision(origin, ray, min, max, |index, min, max| {
+            let mut min = min;
+            while let Some((mut hit, lighting_props)) = self.shapes[index].check_lit_collision(origin, ray, min, max) {
+                if lighting_props.transp <= 0.0 {
+                    return true;
+                }
+                hit.add_shape_index(index, self.shapes.len());
+                if !crossed.contains(&hit.shape_id) {
+                    crossed.push(hit.shape_id);
+                    transmission *= lighting_props.transp;
+                }
+                min = hit.t + 0.0000001;
+            }
+            return false;
+        });
+        return match blocked {
+            true => 0.0,
+            false => transmission,
+        };
+    }
 
-    pub fn compute_light_color(&self, col_pt: &Vector4, ray: &Vector4, normal: &Vector4, shape: &dyn Shape) -> Vector4{
-        let LightingProps {color: shape_color, amb, diff, spec, refl: _, bright} = shape.lighting_props();
+    pub fn compute_light_color(&self, col_pt: &Vector4, ray: &Vector4, normal: &Vector4, surface: &Surface) -> Vector4{
+        let Surface {color: shape_color, amb, diff, spec, bright, ..} = surface;
         let mut light_color = self.amb_color.clone();
         light_color *= *amb;
         light_color *= shape_color;
@@ -107,11 +128,28 @@ impl RenderData{
             shadow_ray -= col_pt;
 
             let dot = shadow_ray.dot(normal);
-            if dot < 0.0 || self.check_collisions(col_pt, &shadow_ray, 0.000000001, 1.0).is_some()  {
+            if dot < 0.0 {
+                continue;
+            }
+            let transmission = self.shadow_transmission(col_pt, &shadow_ray, 0.000000001, 1.0);
+            if transmission <= 0.0 {
+                continue;
+            }
+            let mut intensity = light.intensity.clone();
+            intensity *= transmission;
+            if let ShadingModel::Pbr{roughness, metallic} = surface.model {
+                let mut to_light = shadow_ray.clone();
+                to_light.normalize();
+                let mut to_eye = ray.clone();
+                to_eye *= -1.0;
+                to_eye.normalize();
+                let mut pbr_color = pbr::reflected_light(shape_color, roughness, metallic, normal, &to_light, &to_eye);
+                pbr_color *= &intensity;
+                light_color += &pbr_color;
                 continue;
             }
             
-            let mut diff_color = light.intensity.clone();
+            let mut diff_color = intensity.clone();
             diff_color *= (dot * diff) / shadow_ray.len();
             diff_color *= shape_color;
             light_color += &diff_color; //Computed and added diffuse light
@@ -130,7 +168,7 @@ impl RenderData{
                 shininess /= ref_ray.len();
                 shininess = shininess.powf(*bright);
 
-                let mut spec_color = light.intensity.clone();
+                let mut spec_color = intensity;
                 spec_color *= shininess * spec;
 
                 light_color += &spec_color;
@@ -139,15 +177,37 @@ impl RenderData{
         return light_color;
     }
     
-    pub fn traceray(&self, origin :&Vector4, ray: &Vector4, min_t:f64, bounce_ct : i32) -> Vector4{
+    //Traces a ray into the scene and gives the colour of the light coming back along it. Rays that hit nothing give the miss colour,
+    //which is the background for rays from the eye and black for reflected rays. The media are the number of each transparent shape
+    //the ray is inside of, as given by the shape_id of its hits, along with its index of refraction, from the outermost to the innermost.
+    //They are empty for rays travelling through air.
+    pub fn traceray(&self, origin :&Vector4, ray: &Vector4, min_t:f64, bounce_ct : i32, miss_color: &Vector4, media: &[(usize, f64)]) -> Vector4{
         let color = match self.check_collisions(&origin, &ray, min_t, std::f64::INFINITY) {
-            None => match bounce_ct < NUM_BOUNCES { //If this is a bounced ray, return black if there were no collisions.
-                true => Vector4::vec(0.0, 0.0, 0.0),
-                false => self.back_color.clone(),
-            },
-            Some((shape, col_pt, normal)) => {
-                let mut color = self.compute_light_color(&col_pt, &ray, &normal, shape);
+            None => miss_color.clone(),
+            Some((lighting_props, hit)) => {
+                let col_pt = hit.point;
+                let normal = lighting_props.shading_normal(&hit);
+                let surface = lighting_props.surface_at(&hit);
+                let mut color = self.compute_light_color(&col_pt, &ray, &normal, &surface);
+                if surface.transp > 0.0 {
+                    color *= 1.0 - surface.transp;
+                }
                 if bounce_ct > 0 {
+                    //Physically based surfaces reflect by their own reflectance instead of kR.
+                    let mut refl = match surface.model {
+                        ShadingModel::Phong => surface.refl,
+                        ShadingModel::Pbr{..} => 0.0,
+                    };
+                    if surface.transp > 0.0 {
+                        let (fresnel, refraction) = refract(ray, &normal, hit.front_face, (hit.shape_id, surface.ior), media);
+                        refl += surface.transp * fresnel;
+                        if let Some((refr_ray, refr_media)) = refraction {
+                            let mut refr_color = self.traceray(&col_pt, &refr_ray, 0.0000001, bounce_ct - 1, miss_color, &refr_media);
+                            refr_color *= surface.transp * (1.0 - fresnel);
+                            color += &refr_color;
+                        }
+                    }
+
                     let dot = 2.0 * ray.dot(&normal);
                     let mut bounce = normal;
                     bounce *= dot;
@@ -155,8 +215,19 @@ impl RenderData{
                     let mut refl_ray = ray.clone();
                     refl_ray -= &bounce;
 
-                    let mut ref_color = self.traceray(&col_pt, &refl_ray, 0.0000001, bounce_ct - 1);
-                    ref_color *= shape.lighting_props().refl;
+                    let mut ref_color = self.traceray(&col_pt, &refl_ray, 0.0000001, bounce_ct - 1, &Vector4::vec(0.0, 0.0, 0.0), media);
+                    match surface.model {
+                        ShadingModel::Phong => ref_color *= refl,
+                        ShadingModel::Pbr{roughness, metallic} => {
+                            let mut to_eye = ray.clone();
+                            to_eye *= -1.0;
+                            to_eye.normalize();
+                            let mut reflectance = pbr::mirror_reflectance(&surface.color, roughness, metallic, &normal, &to_eye);
+                            reflectance *= 1.0 - surface.transp;
+                            reflectance += &Vector4::vec(refl, refl, refl);
+                            ref_color *= &reflectance;
+                        },
+                    }
                     color += &ref_color;
                 }
                 color
@@ -179,7 +250,7 @@ impl RenderData{
                 let pixel_center_y = self.top - pixel_height * (px_y as f64 + 0.5);
                 ray.arr[0] = pixel_center_x;
                 ray.arr[1] = pixel_center_y;
-                let color = self.traceray(&eye, &ray, 1.0000001, NUM_BOUNCES);
+                let color = self.traceray(&eye, &ray, 1.0000001, NUM_BOUNCES, &self.back_color, &[]);
                 average_color += &color;
 
                 for i in 0..extra_points{
@@ -189,7 +260,7 @@ impl RenderData{
                     let variance_y = 0.65 * angle.sin();
                     ray.arr[0] =  pixel_center_x + pixel_width * variance_x; 
                     ray.arr[1] =  pixel_center_y + pixel_height * variance_y;
-                    let mut color = self.traceray(&eye, &ray, 1.0000001, NUM_BOUNCES);
+                    let mut color = self.traceray(&eye, &ray, 1.0000001, NUM_BOUNCES, &self.back_color, &[]);
                     color *= 0.7;
                     average_color += &color;
                     num_samples += 0.7;
@@ -287,6 +358,247 @@ impl RenderData{
         return Some(param_val);
     }
 
+    //Reads a shape from a line of the description file, or returns None if the line does not describe a shape.
+    //Shapes that are made up of other shapes read the lines describing their parts from lines.
+    //Instances refer to the objects defined earlier in the file.
+    //Material options at the end of the line are split off first and applied to the whole shape.
+    pub fn read_shape(tokens: &Vec<&str>, line: &String, lines: &mut dyn Iterator<Item = String>, objects: &HashMap<String, Arc<SharedObject>>) -> Result<Option<Box<dyn Shape>>, io::Error>{
+        let (tokens, material_tokens) = material::split_material_tokens(tokens);
+        let tokens = &tokens;
+        let shape: Box<dyn Shape> = match tokens[0] {
+            "SPHERE" => {
+                match Sphere::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read sphere from {line}."))),
+                    Some(sphere) => Box::new(sphere),
+                }
+            },
+            "CUBE" => {
+                match Cube::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read cube from {line}."))),
+                    Some(cube) => Box::new(cube),
+                }
+            },
+            "TETRA" => {
+                match Tetrahedron::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read tetrahedron from {line}."))),
+                    Some(tetra) => Box::new(tetra),
+                }
+            },
+            "DODA" => {
+                match Dodecahedron::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read dodecahedron from {line}."))),
+                    Some(doda) => Box::new(doda),
+                }
+            },
+            "OCTA" => {
+                match Octahedron::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read octahedron from {line}."))),
+                    Some(octa) => Box::new(octa),
+                }
+            },
+            "ICOSA" => {
+                match Icosahedron::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read icosahedron from {line}."))),
+                    Some(icosa) => Box::new(icosa),
+                }
+            },
+            "GEODESIC" => {
+                match GeodesicSphere::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read geodesic sphere from {line}."))),
+                    Some(geodesic) => Box::new(geodesic),
+                }
+            },
+            "CONVEX" => {
+                match ConvexPolyhedron::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read convex polyhedron from {line}."))),
+                    Some(convex) => Box::new(convex),
+                }
+            },
+            "PLANE" => {
+                match Plane::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read plane from {line}."))),
+                    Some(plane) => Box::new(plane),
+                }
+            },
+            "QUAD" => {
+                match Quad::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read quad from {line}."))),
+                    Some(quad) => Box::new(quad),
+                }
+            },
+            "DISK" => {
+                match Disk::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read disk from {line}."))),
+                    Some(disk) => Box::new(disk),
+                }
+            },
+            "POLYGON" => {
+                match FlatPolygon::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read polygon from {line}."))),
+                    Some(polygon) => Box::new(polygon),
+                }
+            },
+            "CYLINDER" => {
+                match Cylinder::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read cylinder from {line}."))),
+                    Some(cylinder) => Box::new(cylinder),
+                }
+            },
+            "CONE" => {
+                match Cone::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read cone from {line}."))),
+                    Some(cone) => Box::new(cone),
+                }
+            },
+            "TORUS" => {
+                match Torus::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read torus from {line}."))),
+                    Some(torus) => Box::new(torus),
+                }
+            },
+            "QUADRIC" => {
+                match Quadric::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read quadric from {line}."))),
+                    Some(quadric) => Box::new(quadric),
+                }
+            },
+            "LATHE" => {
+                match Lathe::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read lathe from {line}."))),
+                    Some(lathe) => Box::new(lathe),
+                }
+            },
+            "EXTRUDE" => {
+                match Extrusion::read_from_tokens(tokens) {
+                    None => return Err(Error::new(ErrorKind::Other, format!("Could not read extrusion from {line}."))),
+                    Some(extrusion) => Box::new(extrusion),
+                }
+            },
+            "MESH" => {
+                match MeshShape::read_mesh_from_tokens(tokens) {
+                    Err(error) => return Err(error),
+                    Ok(mesh) => Box::new(mesh),
+                }
+            },
+            "BEZIER" => {
+                match BezierSurface::read_from_tokens(tokens) {
+                    Err(error) => return Err(error),
+                    Ok(surface) => Box::new(surface),
+                }
+            },
+            "HEIGHTFIELD" => {
+                match Heightfield::read_from_tokens(tokens) {
+                    Err(error) => return Err(error),
+                    Ok(heightfield) => Box::new(heightfield),
+                }
+            },
+            "UNION" | "INTERSECTION" | "DIFFERENCE" => Box::new(Self::read_csg(tokens, line, lines, objects)?),
+            "INSTANCE" => Box::new(Self::read_instance(tokens, line, objects)?),
+            "GROUP" => Box::new(Self::read_group(tokens, line, lines, objects)?),
+            "SDF" => Box::new(Sdf::read_from_lines(tokens, line, lines)?),
+            &_ => return Ok(None),
+        };
+        if material_tokens.is_empty() {
+            return Ok(Some(shape));
+        }
+        let lighting_props = material::read_material(&material_tokens, shape.lighting_props())?;
+        return Ok(Some(Box::new(MaterialShape::new(shape, lighting_props))));
+    }
+    //Reads a block made up of a line giving the CSG operation, colour and lighting coefficients,
+    //a line for each of the solid shapes to combine and a line containing END.
+    fn read_csg(tokens: &Vec<&str>, line: &String, lines: &mut dyn Iterator<Item = String>, objects: &HashMap<String, Arc<SharedObject>>) -> Result<Csg, io::Error>{
+        let first_token = tokens[0];
+        let operation = match CsgOperation::from_token(first_token) {
+            None => return Err(Error::new(ErrorKind::Other, format!("Could not read CSG operation from {line}."))),
+            Some(operation) => operation,
+        };
+        if tokens.len() != 10 {
+            return Err(Error::new(ErrorKind::Other, format!("Could not read {first_token} from {line}.")));
+        }
+        let mut lighting_values: [f64;8] = [0.0;8];
+        for i in 0..8{
+            match tokens[i + 2].to_string().trim().parse::<f64>(){
+                Err(_e) => return Err(Error::new(ErrorKind::Other, format!("Could not read {first_token} from {line}."))),
+                Ok(num) => lighting_values[i] = num,
+            }
+        }
+        let lighting_props = LightingProps::from_slice(&lighting_values);
+
+        let shapes = Self::read_block_shapes(tokens, line, lines, objects, true)?;
+        return Ok(Csg::new(operation, shapes, lighting_props));
+    }
+
+    //Reads the shapes making up a block, one per line, up to and including the line containing END.
+    //The tokens and line are those of the line that started the block.
+    fn read_block_shapes(tokens: &Vec<&str>, line: &String, lines: &mut dyn Iterator<Item = String>, objects: &HashMap<String, Arc<SharedObject>>, solid_only: bool) -> Result<Vec<Box<dyn Shape>>, io::Error>{
+        let first_token = tokens[0];
+        let mut shapes = Vec::<Box<dyn Shape>>::new();
+        loop {
+            let part_line = match lines.next() {
+                None => return Err(Error::new(ErrorKind::Other, format!("Missing END for {line}."))),
+                Some(part_line) => part_line,
+            };
+            let part_tokens: Vec<&str> = part_line.split_whitespace().collect();
+            if part_tokens.is_empty() || part_tokens[0].starts_with('#') {
+                continue;
+            }
+            if part_tokens[0] == "END" {
+                break;
+            }
+            match Self::read_shape(&part_tokens, &part_line, lines, objects)? {
+                None => return Err(Error::new(ErrorKind::Other, format!("Only shapes can be part of {first_token}, not {part_line}."))),
+                Some(shape) if solid_only && !shape.is_solid() => return Err(Error::new(ErrorKind::Other, format!("Only solid shapes can be part of {first_token}, not {part_line}."))),
+                Some(shape) => shapes.push(shape),
+            }
+        }
+        if shapes.is_empty() {
+            return Err(Error::new(ErrorKind::Other, format!("No shapes were given for {line}.")));
+        }
+        return Ok(shapes);
+    }
+    //Reads a block made up of a line giving the name, position, scale and optional rotations of a group,
+    //a line for each of the shapes in it and a line containing END.
+    //The group is placed like an instance of an object that is only used once, so its transform is applied
+    //on top of the transforms of the shapes in it.
+    fn read_group(tokens: &Vec<&str>, line: &String, lines: &mut dyn Iterator<Item = String>, objects: &HashMap<String, Arc<SharedObject>>) -> Result<Instance, io::Error>{
+        let spatial_props = match read_spatial_props_from_tokens(tokens) {
+            None => return Err(Error::new(ErrorKind::Other, format!("Could not read group from {line}."))),
+            Some(spatial_props) => spatial_props,
+        };
+        let shapes = Self::read_block_shapes(tokens, line, lines, objects, false)?;
+        let object = SharedObject::new(tokens[1].to_string(), shapes);
+        return Ok(Instance::new(Arc::new(object), spatial_props, None));
+    }
+    //Reads a block made up of a line naming an object, a line for each of the shapes it is made of and a line containing END.
+    fn read_object(tokens: &Vec<&str>, line: &String, lines: &mut dyn Iterator<Item = String>, objects: &HashMap<String, Arc<SharedObject>>) -> Result<SharedObject, io::Error>{
+        if tokens.len() != 2 {
+            return Err(Error::new(ErrorKind::Other, format!("Could not read object from {line}.")));
+        }
+        let shapes = Self::read_block_shapes(tokens, line, lines, objects, false)?;
+        return Ok(SharedObject::new(tokens[1].to_string(), shapes));
+    }
+    //Reads an INSTANCE line, which gives the name of the object to place after its own name, followed by the position, scale
+    //and optional rotations of the instance. The colour and lighting coefficients may follow to override those of the object.
+    fn read_instance(tokens: &Vec<&str>, line: &String, objects: &HashMap<String, Arc<SharedObject>>) -> Result<Instance, io::Error>{
+        if tokens.len() < 3 {
+            return Err(Error::new(ErrorKind::Other, format!("Could not read instance from {line}.")));
+        }
+        let object = match objects.get(tokens[2]) {
+            None => return Err(Error::new(ErrorKind::Other, format!("No object named {} has been defined before {line}.", tokens[2]))),
+            Some(object) => Arc::clone(object),
+        };
+        let mut shape_tokens = tokens.clone();
+        shape_tokens.remove(2);
+        let props = match shape_tokens.len() > 11 {
+            true => read_props_from_tokens(&shape_tokens).map(|(spatial_props, lighting_props)| (spatial_props, Some(lighting_props))),
+            false => read_spatial_props_from_tokens(&shape_tokens).map(|spatial_props| (spatial_props, None)),
+        };
+        return match props {
+            None => Err(Error::new(ErrorKind::Other, format!("Could not read instance from {line}."))),
+            Some((spatial_props, lighting_props)) => Ok(Instance::new(object, spatial_props, lighting_props)),
+        };
+    }
+
     pub fn read_from_file(filename: &String) -> Result<Self, io::Error>{
         let path = Path::new(&filename);
         let file_result = File::open(&path);
@@ -294,12 +606,10 @@ impl RenderData{
             return Err(Error::new(ErrorKind::Other, format!("Couldn't open {filename} for reading!"))); 
         }
         let mut reader = BufReader::new(file_result.unwrap());
-        let lines = (&mut reader).lines();
+        let mut lines = (&mut reader).lines().map_while(Result::ok);
         
-        let mut spheres = Vec::<Sphere>::new();
-        let mut cubes = Vec::<Cube>::new();
-        let mut tetras = Vec::<Tetrahedron>::new();
-        let mut dodas = Vec::<Dodecahedron>::new();
+        let mut shapes = Vec::<Box<dyn Shape>>::new();
+        let mut objects = HashMap::<String, Arc<SharedObject>>::new();
         let mut lights = Vec::<Light>::new();
 
         let (mut near, mut left, mut right, mut bottom, mut top) = (None, None, None, None, None);
@@ -307,36 +617,23 @@ impl RenderData{
         let mut amb_color: Option<Vector4> = None;
         let mut back_color: Option<Vector4> = None;
         let mut output_file: Option<String> = None;
-        for line in lines.map_while(Result::ok){
+        while let Some(line) = lines.next(){
             let tokens: Vec<&str> = line.split_whitespace().collect();
             if tokens.len() <= 0 {
                 continue;
             }
+            if let Some(shape) = Self::read_shape(&tokens, &line, &mut lines, &objects)? {
+                shapes.push(shape);
+                continue;
+            }
             let first_token = tokens[0];
             match first_token {
-                "SPHERE" => {
-                    match Sphere::read_from_tokens(&tokens) {
-                        None => return Err(Error::new(ErrorKind::Other, format!("Could not read sphere from {line}."))),
-                        Some(sphere) => spheres.push(sphere),
-                    }
-                },
-                "CUBE" => {
-                    match Cube::read_from_tokens(&tokens) {
-                        None => return Err(Error::new(ErrorKind::Other, format!("Could not read sphere from {line}."))),
-                        Some(cube) => cubes.push(cube),
-                    }
-                },
-                "TETRA" => {
-                    match Tetrahedron::read_from_tokens(&tokens) {
-                        None => return Err(Error::new(ErrorKind::Other, format!("Could not read tetrahedron from {line}."))),
-                        Some(tetra) => tetras.push(tetra),
-                    }
-                },
-                "DODA" => {
-                    match Dodecahedron::read_from_tokens(&tokens) {
-                        None => return Err(Error::new(ErrorKind::Other, format!("Could not read tetrahedron from {line}."))),
-                        Some(doda) => dodas.push(doda),
+                "OBJECT" => {
+                    let object = Self::read_object(&tokens, &line, &mut lines, &objects)?;
+                    if objects.contains_key(&object.name) {
+                        return Err(Error::new(ErrorKind::Other, format!("Only one object named {} is permitted!", object.name)));
                     }
+                    objects.insert(object.name.clone(), Arc::new(object));
                 },
                 "LIGHT" => {
                     match Light::read_from_tokens(&tokens){
@@ -405,8 +702,11 @@ impl RenderData{
         let output_ppm_file = output_file.unwrap_or("output.ppm".to_string());
         let output_png_file = output_ppm_file.trim_end_matches(".ppm").to_string() + ".png";
         
+        let boxes: Vec<BoundingBox> = shapes.iter().map(|shape| shape.bounding_box()).collect();
+        let bvh = Bvh::new(&boxes);
+
         let result = Self{near, left, right, bottom, top, width, height, 
-            spheres, tetras, dodas, cubes, lights, back_color, amb_color, output_ppm_file, output_png_file};
+            shapes, bvh, lights, back_color, amb_color, output_ppm_file, output_png_file};
         return Ok(result);
     }
 }
@@ -417,14 +717,8 @@ impl fmt::Display for RenderData{
         write!(f, "Near plane: {}, Horizontal range: {{{},{}}} Vertical range: {{{},{}}}\n", self.near, self.left, self.right, self.bottom, self.top)?;
         write!(f, "Back colour: {}, Ambient colour:{}\n", self.back_color, self.amb_color)?;
         write!(f, "\nShapes:\n")?;
-        for tetra in self.tetras.iter(){
-            write!(f,"\t-{tetra}\n")?;
-        }
-        for cube in self.cubes.iter() {
-            write!(f, "\t-{cube}\n")?;
-        }
-        for sphere in self.spheres.iter() {
-            write!(f, "\t-{sphere}\n")?;
+        for shape in self.shapes.iter(){
+            write!(f, "\t-{shape}\n")?;
         }
         write!(f, "\nLights:\n")?;
         for light in self.lights.iter(){
@@ -434,3 +728,143 @@ impl fmt::Display for RenderData{
         return Ok(());
     }
 }
+
+//Bends a ray that hits a transparent surface into or out of the shape using Snell's law. The medium of the shape is given by
+//the number of the shape and its index of refraction, and the ray travels through the given media before the hit. Gives the share of the light that is reflected instead, found with Schlick's
+//approximation of the Fresnel equations, along with the refracted ray, as long as the original ray, and the media it travels through.
+//There is no refracted ray when the light is totally reflected inside of the denser medium.
+fn refract(ray: &Vector4, normal: &Vector4, front_face: bool, medium: (usize, f64), media: &[(usize, f64)]) -> (f64, Option<(Vector4, Vec<(usize, f64)>)>){
+    let (shape_id, ior) = medium;
+    let mut refr_media = media.to_vec();
+    let (n1, n2) = match front_face {
+        true => {
+            let n1 = media.last().map_or(1.0, |medium| medium.1);
+            refr_media.push(medium);
+            (n1, ior)
+        },
+        false => {
+            //A ray whose entry into the shape was missed is still taken to leave it, but there is no medium of the shape to remove.
+            if let Some(index) = refr_media.iter().rposition(|medium| medium.0 == shape_id) {
+                refr_media.remove(index);
+            }
+            (ior, refr_media.last().map_or(1.0, |medium| medium.1))
+        },
+    };
+    //Light passes straight between media with the same index without being reflected.
+    if n1 == n2 {
+        return (0.0, Some((ray.clone(), refr_media)));
+    }
+
+    let mut direction = ray.clone();
+    direction.normalize();
+    let mut normal = normal.clone();
+    if normal.dot(&direction) > 0.0 {
+        normal *= -1.0;
+    }
+    let cos_i = -normal.dot(&direction);
+    let eta = n1 / n2;
+    let cos_t_sq = 1.0 - eta * eta * (1.0 - cos_i * cos_i);
+    if cos_t_sq < 0.0 {
+        return (1.0, None);
+    }
+    let cos_t = cos_t_sq.sqrt();
+
+    let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
+    let cos = if n1 > n2 { cos_t } else { cos_i };
+    let fresnel = r0 + (1.0 - r0) * (1.0 - cos).powi(5);
+
+    let mut refr_ray = direction;
+    refr_ray *= eta;
+    normal *= eta * cos_i - cos_t;
+    refr_ray += &normal;
+    refr_ray *= ray.len();
+    return (fresnel, Some((refr_ray, refr_media)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Reads a small scene of a checkered floor with the given shape lines in front of it.
+    fn load_scene(name: &str, shape_lines: &str) -> RenderData{
+        let path = std::env::temp_dir().join(format!("rusttracer_{}_{}.txt", name, std::process::id()));
+        let scene = format!("RES 80 40\nLEFT -1\nRIGHT 1\nBOTTOM -0.5\nTOP 0.5\n\
+            PLANE floor 0 -1.5 0 1 1 1 0.8 0.8 0.8 0.2 0.7 0.2 0 20 PATTERN COLOR CHECKER 2 0.1 0.1 0.8 0.9 0.9 0.9\n\
+            {shape_lines}\nLIGHT l1 5 8 0 1 1 1\nBACK 0.6 0.5 0.4\nAMBIENT 0.2 0.2 0.2\n");
+        std::fs::write(&path, scene).unwrap();
+        let render_data = RenderData::read_from_file(&path.to_string_lossy().to_string()).unwrap();
+        let _ = std::fs::remove_file(&path);
+        return render_data;
+    }
+    fn render_scene(name: &str, shape_lines: &str) -> Vec<u8>{
+        return load_scene(name, shape_lines).render(0, 1);
+    }
+    //The number of colour channels that differ by more than the tolerance.
+    fn count_differences(a: &[u8], b: &[u8], tolerance: u8) -> usize{
+        return a.iter().zip(b.iter()).filter(|(a, b)| a.abs_diff(**b) > tolerance).count();
+    }
+
+    #[test]
+    fn transparent_sdf_sphere_refracts_like_sphere(){
+        let empty = render_scene("empty", "");
+        let sphere = render_scene("sphere", "SPHERE glass 0 -0.3 -5 1 1 1 0 0 0 1 1 1 0 0 0 0 20 TRANSPARENT 1 1.5");
+        let sdf = render_scene("sdf", "SDF glass 0 256 0.0001 1 1 1 0 0 0 0 20 TRANSPARENT 1 1.5\nCAPSULE 0 -0.3 -5 0 -0.3 -5 1\nEND");
+        //The lens must bend the floor seen through it, and the SDF must bend it the same way as the sphere.
+        assert!(count_differences(&empty, &sphere, 24) > sphere.len() / 50);
+        assert!(count_differences(&sphere, &sdf, 24) < sphere.len() / 100);
+    }
+
+    #[test]
+    fn refraction_tracks_media_by_shape(){
+        let ray = Vector4::vec(0.0, -1.0, -1.0);
+        let normal = Vector4::vec(0.0, 0.0, 1.0);
+        //Entering one glass shape and then an overlapping one with the same index, then leaving the first.
+        let (_fresnel, entered) = refract(&ray, &normal, true, (0, 1.5), &[]);
+        let (_ray, media) = entered.unwrap();
+        let (_fresnel, entered) = refract(&ray, &normal, true, (1, 1.5), &media);
+        let (_ray, media) = entered.unwrap();
+        assert!(media == vec![(0, 1.5), (1, 1.5)]);
+        let (fresnel, left) = refract(&ray, &normal, false, (0, 1.5), &media);
+        let (left_ray, media) = left.unwrap();
+        assert!(media == vec![(1, 1.5)]);
+        //The ray is still in glass, so it does not bend.
+        assert!(fresnel < 0.0001);
+        assert!((&left_ray - &ray).len() < 0.000001);
+
+        //Leaving a shape that the ray was never seen entering keeps the media it is in.
+        let (_fresnel, left) = refract(&ray, &normal, false, (2, 1.5), &[(0, 1.33)]);
+        assert!(left.unwrap().1 == vec![(0, 1.33)]);
+
+        //Overlapping glass and water inside of a group are told apart, so leaving the glass while inside of the water bends the ray.
+        let render_data = load_scene("group", "GROUP lenses 0 0 0 1 1 1 0 0 0\n\
+            SPHERE glass -0.4 0 -5 1 1 1 0 0 0 1 1 1 0 0 0 0 20 TRANSPARENT 1 1.5\n\
+            SPHERE water 0.4 0 -5 1 1 1 0 0 0 1 1 1 0 0 0 0 20 TRANSPARENT 1 1.33\nEND");
+        let medium_at = |x: f64| {
+            let (lighting_props, hit) = render_data.check_collisions(&Vector4::point(x, 0.0, 0.0), &Vector4::vec(0.0, 0.0, -1.0), 0.0, f64::INFINITY).unwrap();
+            return (hit.shape_id, lighting_props.surface_at(&hit).ior);
+        };
+        let (glass, water) = (medium_at(-1.2), medium_at(1.2));
+        assert!(glass.1 == 1.5 && water.1 == 1.33 && glass.0 != water.0);
+        let (_fresnel, entered) = refract(&ray, &normal, true, glass, &[]);
+        let (_fresnel, entered) = refract(&ray, &normal, true, water, &entered.unwrap().1);
+        let (fresnel, left) = refract(&ray, &normal, false, glass, &entered.unwrap().1);
+        assert!(left.unwrap().1 == vec![water]);
+        assert!(fresnel > 0.0);
+    }
+
+    #[test]
+    fn transparent_shapes_cast_lighter_shadows(){
+        //From the floor straight up through a sphere, and through a second one above it inside of a group.
+        let transmission = |shape_lines: &str| {
+            let render_data = load_scene("shadow", shape_lines);
+            return render_data.shadow_transmission(&Vector4::point(0.0, -1.5, -5.0), &Vector4::vec(0.0, 10.0, 0.0), 0.000000001, 1.0);
+        };
+        let ball = "SPHERE ball 0 0 -5 1 1 1 0 0 0 1 1 1 0.2 0.7 0.5 0 20";
+        assert!(transmission("") == 1.0);
+        assert!(transmission(ball) == 0.0);
+        assert!(transmission(&format!("{ball} TRANSPARENT 0.8 1.5")) == 0.8);
+        assert!(transmission(&format!("{ball} TRANSPARENT 1 1.5")) == 1.0);
+        let group = format!("GROUP balls 0 0 0 1 1 1 0 0 0\n{ball} TRANSPARENT 0.8 1.5\nSPHERE top 0 3 -5 1 1 1 0 0 0 1 1 1 0.2 0.7 0.5 0 20 TRANSPARENT 0.5 1.33\nEND");
+        assert!((transmission(&group) - 0.4).abs() < 1e-12);
+    }
+}
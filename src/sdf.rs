@@ -0,0 +1,318 @@
+use std::fmt;
+use std::io;
+use std::io::{Error, ErrorKind};
+use crate::matrix::Vector4;
+use crate::shape::{LightingProps,Shape,Hit};
+use crate::bounding_box::BoundingBox;
+
+//The Mandelbulb fits inside a sphere of about this radius, which is used for its bounding box.
+const MANDELBULB_RADIUS : f64 = 1.2;
+//Once a point of the Mandelbulb iteration is this far from the center it is known to escape.
+const MANDELBULB_BAILOUT : f64 = 2.0;
+
+//A shape given by its signed distance function, which is negative inside the shape and positive outside of it.
+//Every primitive is placed directly in world space.
+pub enum SdfPrimitive{
+    //A box with the given center and half extents, grown by the rounding radius.
+    RoundedBox{center: Vector4, half_extents: Vector4, radius: f64},
+    //All points within the radius of the segment between the two ends.
+    Capsule{start: Vector4, end: Vector4, radius: f64},
+    //The power 8 Mandelbulb fractal, or another power, scaled around its center.
+    Mandelbulb{center: Vector4, scale: f64, power: f64, iterations: usize},
+}
+
+impl SdfPrimitive{
+    //Reads a line describing a primitive inside an SDF block.
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        let values = tokens[1..].iter().map(|token| token.to_string().trim().parse::<f64>().ok()).collect::<Option<Vec<f64>>>()?;
+        return match (tokens[0], values.len()) {
+            ("ROUNDBOX", 7) => {
+                if values[3..].iter().any(|value| *value < 0.0) {
+                    return None;
+                }
+                Some(Self::RoundedBox{center: Vector4::point_from_slice(&values[0..3]), half_extents: Vector4::vec_from_slice(&values[3..6]), radius: values[6]})
+            },
+            ("CAPSULE", 7) => {
+                if values[6] <= 0.0 {
+                    return None;
+                }
+                Some(Self::Capsule{start: Vector4::point_from_slice(&values[0..3]), end: Vector4::point_from_slice(&values[3..6]), radius: values[6]})
+            },
+            ("MANDELBULB", 6) => {
+                if values[3] <= 0.0 || values[4] < 2.0 || values[5] < 1.0 {
+                    return None;
+                }
+                Some(Self::Mandelbulb{center: Vector4::point_from_slice(&values[0..3]), scale: values[3], power: values[4], iterations: values[5] as usize})
+            },
+            _ => None,
+        };
+    }
+    pub fn distance(&self, point: &Vector4) -> f64{
+        return match self {
+            Self::RoundedBox{center, half_extents, radius} => {
+                let mut outside = 0.0;
+                let mut largest = f64::NEG_INFINITY;
+                for i in 0..3{
+                    let q = (point.arr[i] - center.arr[i]).abs() - half_extents.arr[i];
+                    outside += q.max(0.0) * q.max(0.0);
+                    largest = largest.max(q);
+                }
+                outside.sqrt() + largest.min(0.0) - radius
+            },
+            Self::Capsule{start, end, radius} => {
+                let mut to_point = point.clone();
+                to_point -= start;
+                let mut axis = end.clone();
+                axis -= start;
+                let axis_len_sq = axis.len_sq();
+                let along = match axis_len_sq > 0.0 {
+                    true => (to_point.dot(&axis) / axis_len_sq).clamp(0.0, 1.0),
+                    false => 0.0,
+                };
+                axis *= along;
+                to_point -= &axis;
+                to_point.len() - radius
+            },
+            Self::Mandelbulb{center, scale, power, iterations} => {
+                let mut local = point.clone();
+                local -= center;
+                local *= 1.0 / scale;
+                local.force_vec();
+                scale * Self::mandelbulb_distance(&local, *power, *iterations)
+            },
+        };
+    }
+    //Estimates the distance to the Mandelbulb from the rate at which the iteration escapes.
+    fn mandelbulb_distance(point: &Vector4, power: f64, iterations: usize) -> f64{
+        let mut z = point.clone();
+        let mut derivative = 1.0;
+        let mut radius = z.len();
+        for _i in 0..iterations{
+            if radius > MANDELBULB_BAILOUT {
+                break;
+            }
+            let theta = (z.z() / radius).clamp(-1.0, 1.0).acos() * power;
+            let phi = z.y().atan2(z.x()) * power;
+            derivative = radius.powf(power - 1.0) * power * derivative + 1.0;
+            let scaled = radius.powf(power);
+            z = Vector4::vec(scaled * theta.sin() * phi.cos(), scaled * theta.sin() * phi.sin(), scaled * theta.cos());
+            z += point;
+            radius = z.len();
+            if radius == 0.0 {
+                return 0.0;
+            }
+        }
+        if radius <= 0.0 {
+            return 0.0;
+        }
+        return 0.5 * radius.ln() * radius / derivative;
+    }
+    pub fn bounding_box(&self) -> BoundingBox{
+        return match self {
+            Self::RoundedBox{center, half_extents, radius} => {
+                let mut extent = half_extents.clone();
+                extent += &Vector4::vec(*radius, *radius, *radius);
+                BoundingBox::new(center - &extent, center + &extent)
+            },
+            Self::Capsule{start, end, radius} => {
+                let grow = Vector4::vec(*radius, *radius, *radius);
+                let mut bounds = BoundingBox::from_points(&[start - &grow, start + &grow]);
+                bounds.grow(&BoundingBox::from_points(&[end - &grow, end + &grow]));
+                bounds
+            },
+            Self::Mandelbulb{center, scale, ..} => {
+                let extent = Vector4::vec(MANDELBULB_RADIUS * scale, MANDELBULB_RADIUS * scale, MANDELBULB_RADIUS * scale);
+                BoundingBox::new(center - &extent, center + &extent)
+            },
+        };
+    }
+}
+
+impl fmt::Display for SdfPrimitive{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Self::RoundedBox{center, half_extents, radius} => write!(f, "Rounded box with half extents {} and radius {} located at {}.", half_extents, radius, center),
+            Self::Capsule{start, end, radius} => write!(f, "Capsule of radius {} from {} to {}.", radius, start, end),
+            Self::Mandelbulb{center, scale, power, iterations} => write!(f, "Mandelbulb of power {} with {} iterations and scale {} located at {}.", power, iterations, scale, center),
+        };
+    }
+}
+
+//Blends two distances so that the shapes they belong to flow into each other within the blend radius.
+//With a radius of 0.0 this is the ordinary union.
+fn smooth_min(a: f64, b: f64, blend: f64) -> f64{
+    if blend <= 0.0 {
+        return a.min(b);
+    }
+    let h = (blend - (a - b).abs()).max(0.0) / blend;
+    return a.min(b) - h * h * blend * 0.25;
+}
+
+//A shape made from the smooth union of signed distance primitives, which is found by sphere tracing:
+//the ray is repeatedly moved forward by the distance to the shape until it is within epsilon of the surface.
+pub struct Sdf{
+    pub primitives: Vec<SdfPrimitive>,
+    pub blend: f64,
+    pub max_steps: usize,
+    pub epsilon: f64,
+    pub lighting_props: LightingProps,
+    bounds: BoundingBox,
+}
+
+impl Sdf{
+    pub fn new(primitives: Vec<SdfPrimitive>, blend: f64, max_steps: usize, epsilon: f64, lighting_props: LightingProps) -> Self{
+        let mut bounds = BoundingBox::empty();
+        for primitive in primitives.iter(){
+            bounds.grow(&primitive.bounding_box());
+        }
+        //Blending can only pull the surface out by a quarter of the blend radius.
+        let margin = Vector4::vec(0.25 * blend + epsilon, 0.25 * blend + epsilon, 0.25 * blend + epsilon);
+        bounds.min -= &margin;
+        bounds.max += &margin;
+        return Self{primitives, blend, max_steps, epsilon, lighting_props, bounds};
+    }
+    //Reads an SDF block, made up of a line giving the blend radius, step budget, epsilon, colour and lighting coefficients,
+    //a line for each primitive and a line containing END.
+    pub fn read_from_lines(tokens: &Vec<&str>, line: &String, lines: &mut dyn Iterator<Item = String>) -> Result<Self, io::Error>{
+        if tokens.len() != 13 {
+            return Err(Error::new(ErrorKind::Other, format!("Could not read SDF from {line}.")));
+        }
+        let mut values: [f64;11] = [0.0;11];
+        for i in 0..11{
+            match tokens[i + 2].to_string().trim().parse::<f64>(){
+                Err(_e) => return Err(Error::new(ErrorKind::Other, format!("Could not read SDF from {line}."))),
+                Ok(num) => values[i] = num,
+            }
+        }
+        let (blend, max_steps, epsilon) = (values[0], values[1], values[2]);
+        if blend < 0.0 || max_steps < 1.0 || epsilon <= 0.0 {
+            return Err(Error::new(ErrorKind::Other, format!("The blend radius, steps and epsilon of {line} must be positive.")));
+        }
+        let lighting_props = LightingProps::from_slice(&values[3..]);
+
+        let mut primitives = Vec::<SdfPrimitive>::new();
+        loop {
+            let part_line = match lines.next() {
+                None => return Err(Error::new(ErrorKind::Other, format!("Missing END for {line}."))),
+                Some(part_line) => part_line,
+            };
+            let part_tokens: Vec<&str> = part_line.split_whitespace().collect();
+            if part_tokens.is_empty() || part_tokens[0].starts_with('#') {
+                continue;
+            }
+            if part_tokens[0] == "END" {
+                break;
+            }
+            match SdfPrimitive::read_from_tokens(&part_tokens) {
+                None => return Err(Error::new(ErrorKind::Other, format!("Could not read SDF primitive from {part_line}."))),
+                Some(primitive) => primitives.push(primitive),
+            }
+        }
+        if primitives.is_empty() {
+            return Err(Error::new(ErrorKind::Other, format!("No primitives were given for {line}.")));
+        }
+        return Ok(Self::new(primitives, blend, max_steps as usize, epsilon, lighting_props));
+    }
+
+    pub fn distance(&self, point: &Vector4) -> f64{
+        let mut distance = f64::INFINITY;
+        for primitive in self.primitives.iter(){
+            distance = smooth_min(distance, primitive.distance(point), self.blend);
+        }
+        return distance;
+    }
+    //The hit at a point on the surface, with a normal that faces the ray.
+    fn hit_at(&self, t: f64, point: &Vector4, ray: &Vector4) -> Hit{
+        let mut normal = self.normal_at(point);
+        let front_face = normal.dot(ray) <= 0.0;
+        if !front_face {
+            normal *= -1.0;
+        }
+        let mut hit = Hit::new(t, *point, normal, front_face);
+        (hit.uv, hit.tangent) = Hit::box_coords(point, &normal);
+        return hit;
+    }
+    //The gradient of the distance function, estimated with central differences.
+    fn normal_at(&self, point: &Vector4) -> Vector4{
+        let mut normal = Vector4::zero();
+        for i in 0..3{
+            let mut offset = Vector4::zero();
+            offset.arr[i] = self.epsilon;
+            normal.arr[i] = self.distance(&(point + &offset)) - self.distance(&(point - &offset));
+        }
+        return normal;
+    }
+}
+
+impl Shape for Sdf{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.bounds;
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        let ray_len = ray.len();
+        if ray_len <= 0.0 {
+            return None;
+        }
+        let inv_ray = Vector4::vec(1.0 / ray.x(), 1.0 / ray.y(), 1.0 / ray.z());
+        let (t_start, t_end) = self.bounds.check_collision(origin, &inv_ray, min, max)?;
+
+        //Distances are measured along the ray in world units, so that each step moves exactly the distance to the shape.
+        let mut dir = ray.clone();
+        dir /= ray_len;
+        let (mut s, s_end) = (t_start * ray_len, t_end * ray_len);
+        //Rays leaving the surface start within epsilon of it, so they must get clear of it before a collision counts.
+        //A ray that only reaches the bounding box after its start cannot be leaving the surface.
+        let mut left_surface = t_start > min;
+        //Set once a ray that starts on the surface has got more than epsilon inside of the shape, as refracted rays do.
+        //It then steps by the distance to the surface from the inside until it comes back out through the far side.
+        let mut inside = false;
+        for _i in 0..self.max_steps{
+            let mut point = dir.clone();
+            point *= s;
+            point += origin;
+            point.force_point();
+
+            let distance = self.distance(&point);
+            if inside {
+                if distance > -self.epsilon {
+                    return Some(self.hit_at(s / ray_len, &point, ray));
+                }
+                s -= distance;
+            }
+            else if distance < self.epsilon {
+                if left_surface {
+                    return Some(self.hit_at(s / ray_len, &point, ray));
+                }
+                //Rays leaving a surface they were placed slightly under head out of the shape rather than into it.
+                if distance < -self.epsilon && self.normal_at(&point).dot(ray) < 0.0 {
+                    inside = true;
+                    s -= distance;
+                }
+                else {
+                    s += distance.abs().max(self.epsilon);
+                }
+            }
+            else {
+                left_surface = true;
+                s += distance;
+            }
+            if s > s_end {
+                return None;
+            }
+        }
+        return None;
+    }
+}
+
+impl fmt::Display for Sdf{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Signed distance field of {} primitives with blend radius {}, at most {} steps, epsilon {} and color {}:\n", self.primitives.len(), self.blend, self.max_steps, self.epsilon, self.lighting_props.color)?;
+        for primitive in self.primitives.iter(){
+            write!(f, "\t\t-{}\n", primitive)?;
+        }
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
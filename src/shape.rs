@@ -1,6 +1,14 @@
+use std::fmt;
+use std::sync::Arc;
 use crate::matrix::Vector4;
+use crate::bounding_box::BoundingBox;
+use crate::polygon::SpatialProps;
+use crate::texture::ImageTexture;
+use crate::pattern::{Pattern,PatternTarget};
+use crate::pbr::ShadingModel;
 //use crate::matrix::Matrix4;
 
+#[derive(Clone)]
 pub struct LightingProps {
     pub color: Vector4,
     pub amb: f64,
@@ -8,15 +16,300 @@ pub struct LightingProps {
     pub spec: f64,
     pub refl: f64,
     pub bright: f64,
+    //How much light passes through the surface rather than being scattered by it, from 0 for opaque to 1 for clear.
+    pub transp: f64,
+    //The index of refraction of the inside of the shape, which sets how much light bends as it passes through the surface.
+    pub ior: f64,
+    //How the light reaching the surface is reflected, which is the Phong model unless a shape asks for physically based shading.
+    pub model: ShadingModel,
+    //An image that replaces the colour, looked up with the texture coordinates of each hit.
+    pub texture: Option<Arc<ImageTexture>>,
+    //Procedural patterns that replace the colour or one of the coefficients, applied in order after the texture.
+    pub patterns: Vec<(PatternTarget, Pattern)>,
+    //A height image whose slopes tilt the shading normal, along with how strongly they tilt it.
+    pub bump_map: Option<(Arc<ImageTexture>, f64)>,
+    //An image whose colours give the shading normal in the frame of the tangent, bitangent and normal of each hit.
+    pub normal_map: Option<Arc<ImageTexture>>,
+}
+
+//The colour and lighting coefficients at one point of a surface, once any texture and patterns are applied.
+#[derive(Copy, Clone)]
+pub struct Surface {
+    pub color: Vector4,
+    pub amb: f64,
+    pub diff: f64,
+    pub spec: f64,
+    pub refl: f64,
+    pub bright: f64,
+    pub transp: f64,
+    pub ior: f64,
+    pub model: ShadingModel,
 }
 
 impl LightingProps {
     pub fn new( color: Vector4, amb: f64, diff: f64, spec: f64, refl: f64, bright: f64) -> Self{
-        return Self{color, amb, diff, spec, refl, bright};
+        return Self{color, amb, diff, spec, refl, bright, transp: 0.0, ior: 1.0, model: ShadingModel::Phong, texture: None, patterns: Vec::new(), bump_map: None, normal_map: None};
+    }
+    //Creates lighting properties from a colour followed by the amb, diff, spec, refl and bright coefficients.
+    pub fn from_slice(values: &[f64]) -> Self{
+        let color = Vector4::vec_from_slice(&values[0..3]);
+        return Self::new(color, values[3], values[4], values[5], values[6], values[7]);
+    }
+    //The colour and coefficients of the surface at a hit.
+    pub fn surface_at(&self, hit: &Hit) -> Surface{
+        let mut surface = Surface{color: hit.color.unwrap_or(self.color), amb: self.amb, diff: self.diff, spec: self.spec, refl: self.refl, bright: self.bright, transp: self.transp, ior: self.ior, model: self.model};
+        if let Some(texture) = &self.texture {
+            surface.color = texture.color_at(hit.uv);
+        }
+        for (target, pattern) in self.patterns.iter(){
+            let value = pattern.value_at(&hit.local_point);
+            match target {
+                PatternTarget::Color => surface.color = value,
+                PatternTarget::Amb => surface.amb = value.x(),
+                PatternTarget::Diff => surface.diff = value.x(),
+                PatternTarget::Spec => surface.spec = value.x(),
+                PatternTarget::Refl => surface.refl = value.x(),
+                PatternTarget::Bright => surface.bright = value.x(),
+            }
+        }
+        return surface;
+    }
+    //The unit normal used for shading at a hit, which the normal map and then the bump map tilt
+    //within the frame made by the tangent and normal of the hit. Without either map this is the normal of the hit as it is.
+    pub fn shading_normal(&self, hit: &Hit) -> Vector4{
+        if self.normal_map.is_none() && self.bump_map.is_none() {
+            return hit.normal;
+        }
+        let mut normal = hit.normal.clone();
+        normal.normalize();
+
+        //Makes the tangent perpendicular to the normal, falling back to any perpendicular direction if it lies along the normal.
+        let mut tangent = hit.tangent.clone();
+        let mut along_normal = normal.clone();
+        along_normal *= tangent.dot(&normal);
+        tangent -= &along_normal;
+        if !(tangent.len_sq() > 0.0) {
+            tangent = Hit::any_tangent(&normal);
+        }
+        tangent.normalize();
+        let bitangent = normal.cross(&tangent);
+
+        if let Some(normal_map) = &self.normal_map {
+            //Each channel runs from -1 at 0 to 1 at 1 along the tangent, bitangent and normal in turn.
+            let color = normal_map.color_at(hit.uv);
+            let mut mapped = tangent.clone();
+            mapped *= 2.0 * color.x() - 1.0;
+            let mut along_bitangent = bitangent.clone();
+            along_bitangent *= 2.0 * color.y() - 1.0;
+            mapped += &along_bitangent;
+            let mut along_normal = normal.clone();
+            along_normal *= 2.0 * color.z() - 1.0;
+            mapped += &along_normal;
+            if mapped.len_sq() > 0.0 {
+                mapped.normalize();
+                normal = mapped;
+            }
+        }
+        if let Some((height_map, strength)) = &self.bump_map {
+            //The slopes of the height map across one pixel, found from the heights of the pixels on either side.
+            let (du, dv) = height_map.pixel_size();
+            let (u, v) = hit.uv;
+            let slope_u = (height_map.height_at((u + du, v)) - height_map.height_at((u - du, v))) * 0.5;
+            let slope_v = (height_map.height_at((u, v + dv)) - height_map.height_at((u, v - dv))) * 0.5;
+            let mut tilt = tangent;
+            tilt *= strength * slope_u;
+            let mut along_bitangent = bitangent;
+            along_bitangent *= strength * slope_v;
+            tilt += &along_bitangent;
+            normal -= &tilt;
+            if normal.len_sq() > 0.0 {
+                normal.normalize();
+            }
+            else {
+                normal = hit.normal;
+            }
+        }
+        return normal;
     }
 }
 
-pub trait Shape{
-    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max:f64) -> Option<(f64,Vector4,Vector4)>;
+//A stretch of a ray that lies inside a solid shape, given by the t and world space normal where the ray enters and leaves.
+//The normals point out of the shape.
+#[derive(Copy, Clone)]
+pub struct Interval{
+    pub enter: (f64, Vector4),
+    pub exit: (f64, Vector4),
+}
+
+//Everything known about the point where a ray hits the surface of a shape.
+#[derive(Copy, Clone)]
+pub struct Hit{
+    //How far along the ray the hit is, in multiples of the ray.
+    pub t: f64,
+    //The world space point that was hit.
+    pub point: Vector4,
+    //The world space normal used for shading, which is not necessarily of unit length.
+    //Most shapes turn it to face the ray, so that both sides of open surfaces are lit.
+    pub normal: Vector4,
+    //The texture coordinates of the point, which shapes without them leave at (0, 0).
+    pub uv: (f64, f64),
+    //A world space direction along the surface in which u increases, or any direction along the surface
+    //for shapes without texture coordinates.
+    pub tangent: Vector4,
+    //Whether the ray hit the outside of the surface, the side that its outward normal points to.
+    pub front_face: bool,
+    //Which part of the shape was hit, such as the index of a polygon in a mesh. Shapes with a single surface use 0.
+    pub face_id: usize,
+    //Which shape was hit, counting the shapes inside of groups and instances. Each group numbers its shapes by folding the number
+    //of the shape within it into the number the shape gives its own hit, so every shape in the scene ends up with a number of its own.
+    //Shapes without shapes inside of them use 0.
+    pub shape_id: usize,
+    //The point that was hit in the object space of the shape carrying the material, where procedural patterns are evaluated.
+    //Shapes without an object space leave it as the world space point.
+    pub local_point: Vector4,
+    //The colour that the shape itself gives the point, such as one interpolated from the vertex colours of a mesh,
+    //which replaces the colour of the material. Shapes without colours of their own leave it as None.
+    pub color: Option<Vector4>,
+}
+
+impl Hit{
+    pub fn new(t: f64, point: Vector4, normal: Vector4, front_face: bool) -> Self{
+        let tangent = Self::any_tangent(&normal);
+        return Self{t, point, normal, uv: (0.0, 0.0), tangent, front_face, face_id: 0, shape_id: 0, local_point: point, color: None};
+    }
+    //Folds the index of the shape that was hit, among the given number of shapes, into the number of the shape.
+    pub fn add_shape_index(&mut self, index: usize, count: usize){
+        self.shape_id = self.shape_id.wrapping_mul(count).wrapping_add(index);
+    }
+    //A direction perpendicular to the normal, found by crossing it with the axis it is least aligned with.
+    pub fn any_tangent(normal: &Vector4) -> Vector4{
+        let (x, y, z) = (normal.x().abs(), normal.y().abs(), normal.z().abs());
+        let axis = match x <= y && x <= z {
+            true => Vector4::vec(1.0, 0.0, 0.0),
+            false => match y <= z {
+                true => Vector4::vec(0.0, 1.0, 0.0),
+                false => Vector4::vec(0.0, 0.0, 1.0),
+            },
+        };
+        return axis.cross(normal);
+    }
+    //Sets the texture coordinates along with the object space direction in which u increases,
+    //which is moved into world space. A direction of zero length leaves the tangent as it was.
+    pub fn set_uv(&mut self, uv: (f64, f64), tangent_prime: &Vector4, spatial_props: &SpatialProps){
+        self.uv = uv;
+        let mut tangent_prime = tangent_prime.clone();
+        tangent_prime.force_vec();
+        let mut tangent = &spatial_props.matrix * &tangent_prime;
+        tangent.force_vec();
+        if tangent.len_sq() > 0.0 {
+            self.tangent = tangent;
+        }
+    }
+    //Texture coordinates for shapes without their own, found by projecting the point onto the side of a box around the origin
+    //that the normal faces most, so that each unit of distance covers half of the texture. Also gives the direction in which u increases.
+    pub fn box_coords(point: &Vector4, normal: &Vector4) -> ((f64, f64), Vector4){
+        let (x, y, z) = (normal.x().abs(), normal.y().abs(), normal.z().abs());
+        let (u_axis, v_axis) = match x >= y && x >= z {
+            true => (2, 1),
+            false => match y >= z {
+                true => (0, 2),
+                false => (0, 1),
+            },
+        };
+        let mut tangent = Vector4::zero();
+        tangent.arr[u_axis] = 1.0;
+        let mut along_normal = normal.clone();
+        along_normal *= normal.arr[u_axis] / normal.len_sq();
+        tangent -= &along_normal;
+        return ((0.5 * (point.arr[u_axis] + 1.0), 0.5 * (point.arr[v_axis] + 1.0)), tangent);
+    }
+}
+
+pub trait Shape: fmt::Display + Send + Sync{
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max:f64) -> Option<Hit>;
     fn lighting_props(&self) -> &LightingProps;
-}
\ No newline at end of file
+    //Like check_collision, but also gives the lighting properties of the surface that was hit,
+    //which differ from those of the shape itself for shapes made up of other shapes.
+    fn check_lit_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<(Hit,&LightingProps)>{
+        return self.check_collision(origin, ray, min, max).map(|hit| (hit, self.lighting_props()));
+    }
+    //The placement of the shape, for shapes that have their own object space.
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return None;
+    }
+    //The world space box that contains the shape.
+    fn bounding_box(&self) -> BoundingBox;
+    //Whether the shape encloses a volume, which is needed to combine it with other shapes using CSG.
+    fn is_solid(&self) -> bool{
+        return false;
+    }
+    //Finds every stretch of the whole line origin + t * ray that is inside the shape, in order of t.
+    //Only meaningful for solid shapes.
+    fn check_intervals(&self, _origin: &Vector4, _ray: &Vector4) -> Vec<Interval>{
+        return Vec::new();
+    }
+}
+
+//Pairs up the points at which a line crosses the surface of a solid into the intervals inside it.
+//Each crossing has a t and an outward world space normal, which tells whether the line is entering or leaving.
+pub fn intervals_from_crossings(mut crossings: Vec<(f64, Vector4)>, ray: &Vector4) -> Vec<Interval>{
+    crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut intervals = Vec::<Interval>::with_capacity(crossings.len() / 2);
+    let mut enter: Option<(f64, Vector4)> = None;
+    for crossing in crossings.into_iter(){
+        let entering = crossing.1.dot(ray) < 0.0;
+        match (entering, enter) {
+            (true, None) => enter = Some(crossing),
+            (false, Some(enter_crossing)) => {
+                intervals.push(Interval{enter: enter_crossing, exit: crossing});
+                enter = None;
+            },
+            //A second entry before an exit, or an exit without an entry, happens when the line passes through
+            //an edge shared by two faces and hits both of them. Only the first of these is kept.
+            _ => {},
+        }
+    }
+    return intervals;
+}
+
+//Reads the position, scale and optional rotations that place a shape, which follow its keyword and name.
+pub fn read_spatial_props_from_tokens(tokens: &[&str]) -> Option<SpatialProps>{
+    if tokens.len() < 8 || tokens.len() > 11 {
+        return None
+    }
+
+    //Parse the tokens into f64s...
+    let token_slice: &[&str] = &tokens[2..];
+    let mut parsed_tokens: [f64;9] = [0.0;9];
+    for i in 0..token_slice.len(){
+        let parse_result = token_slice[i].to_string().trim().parse::<f64>();
+        match parse_result{
+            Err(_e) => return None,
+            Ok(num) => parsed_tokens[i] = num,
+        }
+    }
+
+    let pos = Vector4::point_from_slice(&parsed_tokens[0..3]);
+    let scale = Vector4::vec_from_slice(&parsed_tokens[3..6]);
+    //Rotations that are left out are 0.0, which is what the unparsed tokens hold.
+    let (r_x, r_y, r_z) = (parsed_tokens[6], parsed_tokens[7], parsed_tokens[8]);
+    return Some(SpatialProps::new(pos, scale, r_x, r_y, r_z));
+}
+
+//Reads the position, scale, optional rotations, colour and lighting coefficients shared by the shape lines.
+pub fn read_props_from_tokens(tokens: &Vec<&str>) -> Option<(SpatialProps,LightingProps)>{
+    if tokens.len() < 16 || tokens.len() > 19 {
+        return None
+    }
+    let color_start = tokens.len() - 8;
+    let spatial_props = read_spatial_props_from_tokens(&tokens[..color_start])?;
+
+    let mut lighting_values: [f64;8] = [0.0;8];
+    for i in 0..8{
+        match tokens[color_start + i].to_string().trim().parse::<f64>(){
+            Err(_e) => return None,
+            Ok(num) => lighting_values[i] = num,
+        }
+    }
+    return Some((spatial_props, LightingProps::from_slice(&lighting_values)));
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+use crate::matrix::Vector4;
+use crate::shape::{LightingProps,Shape,Interval,Hit,read_props_from_tokens};
+use crate::polygon::{SpatialProps,Polygon};
+use crate::mesh_shape::MeshShape;
+use crate::elements::FlatPolygon;
+use crate::bounding_box::BoundingBox;
+
+//Lathes with more steps around their axis than this would have far more faces than they need.
+const MAX_LATHE_STEPS : usize = 4096;
+//The number of straight pieces each span of a spline profile is split into.
+const SPLINE_PIECES : usize = 8;
+
+//A point on the profile of a lathe, with its distance from the axis, its height and the outward normal of the profile there.
+#[derive(Copy, Clone)]
+struct ProfilePoint{
+    r: f64,
+    y: f64,
+    normal: (f64, f64),
+}
+
+//The outward normal of a profile heading in the given direction, which has the outside on its right.
+fn profile_normal(dr: f64, dy: f64) -> (f64, f64){
+    let len = (dr * dr + dy * dy).sqrt();
+    if len <= 0.0 {
+        return (0.0, 0.0);
+    }
+    return (dy / len, -dr / len);
+}
+
+//The surface swept out by revolving a profile in the XY plane of object space around the y axis.
+//The profile is either a polyline, whose straight pieces become cones and cylinders with sharp edges between them,
+//or a Catmull-Rom spline through its points, which is shaded smoothly along its whole length.
+pub struct Lathe{
+    pub mesh_shape: MeshShape,
+    pub steps: usize,
+    pub spline: bool,
+    //Whether both ends of the profile lie on the axis, so that the surface encloses a volume.
+    closed: bool,
+}
+
+impl Lathe{
+    //Reads a LATHE line, which has the number of steps around the axis after the name, then optionally SPLINE,
+    //then the number of profile points and the distance from the axis and height of each. The rest is laid out like a sphere.
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        if tokens.len() < 4 {
+            return None;
+        }
+        let steps = tokens[2].to_string().trim().parse::<usize>().ok()?;
+        if steps < 3 || steps > MAX_LATHE_STEPS {
+            return None;
+        }
+        let spline = tokens[3] == "SPLINE";
+        let count_index = if spline { 4 } else { 3 };
+        let point_count = tokens.get(count_index)?.to_string().trim().parse::<usize>().ok()?;
+        let points_end = count_index + 1 + 2 * point_count;
+        if point_count < 2 || tokens.len() < points_end {
+            return None;
+        }
+        let mut points = Vec::<(f64, f64)>::with_capacity(point_count);
+        for coords in tokens[(count_index + 1)..points_end].chunks(2){
+            let r = coords[0].to_string().trim().parse::<f64>().ok()?;
+            let y = coords[1].to_string().trim().parse::<f64>().ok()?;
+            if r < 0.0 {
+                return None;
+            }
+            points.push((r, y));
+        }
+
+        let mut shape_tokens = tokens.clone();
+        shape_tokens.drain(2..points_end);
+        let (spatial_props, lighting_props) = read_props_from_tokens(&shape_tokens)?;
+        let segments = match spline {
+            true => Self::spline_segments(&points),
+            false => Self::polyline_segments(&points),
+        };
+        if segments.is_empty() {
+            return None;
+        }
+
+        //The texture runs once around the axis in u and along the length of the profile in v.
+        let mut lengths = vec![0.0];
+        for (start, end) in segments.iter(){
+            let length = ((end.r - start.r).powi(2) + (end.y - start.y).powi(2)).sqrt();
+            lengths.push(lengths[lengths.len() - 1] + length);
+        }
+        let total_length = lengths[lengths.len() - 1];
+
+        let mut polygons = Vec::<Polygon>::with_capacity(2 * steps * segments.len());
+        for (index, (start, end)) in segments.iter().enumerate(){
+            let (v_start, v_end) = (lengths[index] / total_length, lengths[index + 1] / total_length);
+            for step in 0..steps{
+                let (u_start, u_end) = (step as f64 / steps as f64, (step + 1) as f64 / steps as f64);
+                //The corners of the band between two steps, counter-clockwise when viewed from outside.
+                let corners = [(start, u_start, v_start), (start, u_end, v_start), (end, u_end, v_end), (end, u_start, v_end)];
+                for triangle in [[0, 1, 2], [0, 2, 3]]{
+                    let triangle = triangle.map(|corner| corners[corner]);
+                    let points = triangle.map(|(profile_point, u, _v)| Self::revolve(profile_point.r, profile_point.y, u, true));
+                    if Polygon::winding_normal(&points).len_sq() <= 0.0 {
+                        continue;
+                    }
+                    let mut polygon = Polygon::from_winding(points.to_vec(), &spatial_props);
+                    polygon.set_vertex_normals(triangle.map(|(profile_point, u, _v)| Self::revolve(profile_point.normal.0, profile_point.normal.1, u, false)).to_vec());
+                    polygon.set_vertex_uvs(triangle.map(|(_profile_point, u, v)| (u, v)).to_vec());
+                    polygons.push(polygon);
+                }
+            }
+        }
+
+        let closed = points[0].0 == 0.0 && points[point_count - 1].0 == 0.0;
+        let mesh_shape = MeshShape::new(spatial_props, lighting_props, polygons);
+        return Some(Self{mesh_shape, steps, spline, closed});
+    }
+    //Turns a distance from the axis and a height into a point or vector the given fraction of a turn around the axis.
+    fn revolve(r: f64, y: f64, turn: f64, is_point: bool) -> Vector4{
+        let angle = 2.0 * std::f64::consts::PI * turn;
+        let mut revolved = Vector4::vec(r * angle.cos(), y, -r * angle.sin());
+        if is_point {
+            revolved.force_point();
+        }
+        return revolved;
+    }
+    //Each straight piece of a polyline is shaded with its own normal, leaving sharp edges between them.
+    fn polyline_segments(points: &[(f64, f64)]) -> Vec<(ProfilePoint, ProfilePoint)>{
+        let mut segments = Vec::<(ProfilePoint, ProfilePoint)>::with_capacity(points.len() - 1);
+        for pair in points.windows(2){
+            let ((r1, y1), (r2, y2)) = (pair[0], pair[1]);
+            if r1 == r2 && y1 == y2 {
+                continue;
+            }
+            let normal = profile_normal(r2 - r1, y2 - y1);
+            segments.push((ProfilePoint{r: r1, y: y1, normal}, ProfilePoint{r: r2, y: y2, normal}));
+        }
+        return segments;
+    }
+    //Splits a Catmull-Rom spline through the points into straight pieces, with the normal of the spline at the end of each piece.
+    //The first and last points are repeated so that the spline runs through every point.
+    fn spline_segments(points: &[(f64, f64)]) -> Vec<(ProfilePoint, ProfilePoint)>{
+        let last = points.len() - 1;
+        let mut samples = Vec::<ProfilePoint>::with_capacity(last * SPLINE_PIECES + 1);
+        for span in 0..last{
+            let p0 = points[span.saturating_sub(1)];
+            let (p1, p2) = (points[span], points[span + 1]);
+            let p3 = points[(span + 2).min(last)];
+            let pieces = if span == last - 1 { SPLINE_PIECES + 1 } else { SPLINE_PIECES };
+            for piece in 0..pieces{
+                let t = piece as f64 / SPLINE_PIECES as f64;
+                let point = |a: f64, b: f64, c: f64, d: f64| {
+                    return 0.5 * (2.0 * b + (c - a) * t + (2.0 * a - 5.0 * b + 4.0 * c - d) * t * t + (3.0 * b - a - 3.0 * c + d) * t * t * t);
+                };
+                let slope = |a: f64, b: f64, c: f64, d: f64| {
+                    return 0.5 * ((c - a) + 2.0 * (2.0 * a - 5.0 * b + 4.0 * c - d) * t + 3.0 * (3.0 * b - a - 3.0 * c + d) * t * t);
+                };
+                let (r, y) = (point(p0.0, p1.0, p2.0, p3.0).max(0.0), point(p0.1, p1.1, p2.1, p3.1));
+                let mut normal = profile_normal(slope(p0.0, p1.0, p2.0, p3.0), slope(p0.1, p1.1, p2.1, p3.1));
+                //Where two points repeat the spline stops, so the direction of the chord is used instead.
+                if normal == (0.0, 0.0) {
+                    normal = profile_normal(p2.0 - p1.0, p2.1 - p1.1);
+                }
+                samples.push(ProfilePoint{r, y, normal});
+            }
+        }
+        return samples.windows(2)
+            .filter(|pair| pair[0].r != pair[1].r || pair[0].y != pair[1].y)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+    }
+}
+impl Shape for Lathe{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.mesh_shape.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.mesh_shape.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.mesh_shape.bounding_box();
+    }
+    //A profile that starts or ends away from the axis leaves a hole in the surface.
+    fn is_solid(&self) -> bool{
+        return self.closed;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        return self.mesh_shape.check_intervals(origin, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        return self.mesh_shape.check_collision(origin, ray, min, max);
+    }
+}
+impl fmt::Display for Lathe{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let spatial_props = &self.mesh_shape.spatial_props;
+        let lighting_props = &self.mesh_shape.lighting_props;
+        let profile = if self.spline { "spline" } else { "polyline" };
+        write!(f, "Lathe of a {} profile in {} steps with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", profile, self.steps, spatial_props.scale, spatial_props.r_x, spatial_props.r_y, spatial_props.r_z, lighting_props.color, spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", lighting_props.amb, lighting_props.diff, lighting_props.spec, lighting_props.refl, lighting_props.bright);
+    }
+}
+
+//A polygon in the XZ plane of object space swept upwards along the y axis, with flat caps at both ends.
+//The polygon may be concave and may have holes, which become tunnels through the solid.
+pub struct Extrusion{
+    pub mesh_shape: MeshShape,
+    pub depth: f64,
+}
+
+impl Extrusion{
+    //Reads an EXTRUDE line, which has the depth after the name followed by the outline and holes laid out as for a polygon.
+    //The rest is laid out like a sphere.
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        if tokens.len() < 3 {
+            return None;
+        }
+        let depth = tokens[2].to_string().trim().parse::<f64>().ok()?;
+        if !(depth > 0.0) {
+            return None;
+        }
+        let (mut outlines, next) = FlatPolygon::read_outlines(tokens, 3)?;
+
+        let mut shape_tokens = tokens.clone();
+        shape_tokens.drain(2..next);
+        let (spatial_props, lighting_props) = read_props_from_tokens(&shape_tokens)?;
+
+        //The outline is wound counter-clockwise when viewed from above and the holes the other way,
+        //so that the walls built along each of them face out of the solid.
+        for (index, outline) in outlines.iter_mut().enumerate(){
+            let winding = Polygon::winding_normal(outline).y();
+            if winding == 0.0 {
+                return None;
+            }
+            if (winding > 0.0) != (index == 0) {
+                outline.reverse();
+            }
+        }
+
+        let lift = |point: &Vector4| {
+            let mut lifted = point.clone();
+            lifted.arr[1] = depth;
+            return lifted;
+        };
+        let mut polygons = Vec::<Polygon>::new();
+        for outline in outlines.iter(){
+            for i in 0..outline.len(){
+                let (a, b) = (outline[i], outline[(i + 1) % outline.len()]);
+                if a.arr == b.arr {
+                    continue;
+                }
+                polygons.push(Polygon::from_winding(vec![a, b, lift(&b), lift(&a)], &spatial_props));
+            }
+        }
+        let top = outlines.iter().map(|outline| outline.iter().map(lift).collect()).collect::<Vec<Vec<Vector4>>>();
+        let mut bottom = outlines;
+        bottom[0].reverse();
+        let (mut top, mut bottom) = (top.into_iter(), bottom.into_iter());
+        polygons.push(Polygon::with_holes(top.next()?, top.collect(), &spatial_props));
+        polygons.push(Polygon::with_holes(bottom.next()?, bottom.collect(), &spatial_props));
+
+        let mesh_shape = MeshShape::new(spatial_props, lighting_props, polygons);
+        return Some(Self{mesh_shape, depth});
+    }
+}
+impl Shape for Extrusion{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.mesh_shape.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.mesh_shape.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        return self.mesh_shape.bounding_box();
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        return self.mesh_shape.check_intervals(origin, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        return self.mesh_shape.check_collision(origin, ray, min, max);
+    }
+}
+impl fmt::Display for Extrusion{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let spatial_props = &self.mesh_shape.spatial_props;
+        let lighting_props = &self.mesh_shape.lighting_props;
+        write!(f, "Extrusion of a polygon with {} faces to depth {} with scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.mesh_shape.polygons.len(), self.depth, spatial_props.scale, spatial_props.r_x, spatial_props.r_y, spatial_props.r_z, lighting_props.color, spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", lighting_props.amb, lighting_props.diff, lighting_props.spec, lighting_props.refl, lighting_props.bright);
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+use std::io;
+use std::io::{Error, ErrorKind};
+use crate::matrix::Vector4;
+
+//What happens to texture coordinates outside of 0 to 1.
+#[derive(Copy, Clone, PartialEq)]
+pub enum WrapMode{
+    //The image is tiled over and over.
+    Repeat,
+    //The edges of the image are stretched out.
+    Clamp,
+}
+
+//How colours are looked up between the centres of the pixels of the image.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Filter{
+    //The colour of the nearest pixel.
+    Nearest,
+    //A blend of the four nearest pixels.
+    Bilinear,
+}
+
+//An image that is wrapped around the surface of a shape using its texture coordinates.
+//u runs from the left of the image to the right and v from the bottom to the top.
+pub struct ImageTexture{
+    pub filename: String,
+    width: usize,
+    height: usize,
+    //The colours of the pixels, in rows from the top of the image.
+    pixels: Vec<Vector4>,
+    pub wrap: WrapMode,
+    pub filter: Filter,
+}
+
+impl ImageTexture{
+    pub fn load(filename: &str, wrap: WrapMode, filter: Filter) -> Result<Self, io::Error>{
+        let image = match image::open(filename) {
+            Err(e) => return Err(Error::new(ErrorKind::Other, format!("Couldn't read texture image {filename}: {e}"))),
+            Ok(image) => image.into_rgb32f(),
+        };
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        if width == 0 || height == 0 {
+            return Err(Error::new(ErrorKind::Other, format!("Texture image {filename} is empty.")));
+        }
+        let pixels = image.pixels().map(|pixel| Vector4::vec(pixel.0[0] as f64, pixel.0[1] as f64, pixel.0[2] as f64)).collect();
+        return Ok(Self{filename: filename.to_string(), width, height, pixels, wrap, filter});
+    }
+
+    //Turns a pixel index that may lie outside of the image into one inside of it.
+    fn wrap_index(&self, index: i64, size: usize) -> usize{
+        return match self.wrap {
+            WrapMode::Repeat => index.rem_euclid(size as i64) as usize,
+            WrapMode::Clamp => index.clamp(0, size as i64 - 1) as usize,
+        };
+    }
+    fn pixel(&self, x: i64, y: i64) -> Vector4{
+        return self.pixels[self.wrap_index(y, self.height) * self.width + self.wrap_index(x, self.width)];
+    }
+
+    //The colour of the image at the given texture coordinates.
+    pub fn color_at(&self, uv: (f64, f64)) -> Vector4{
+        //Pixel coordinates, measured so that the centre of the top left pixel is at (0, 0).
+        let x = uv.0 * self.width as f64 - 0.5;
+        let y = (1.0 - uv.1) * self.height as f64 - 0.5;
+        if !(x.is_finite() && y.is_finite()) {
+            return self.pixels[0];
+        }
+        if self.filter == Filter::Nearest {
+            return self.pixel(x.round() as i64, y.round() as i64);
+        }
+
+        let (left, top) = (x.floor(), y.floor());
+        let (fx, fy) = (x - left, y - top);
+        let (left, top) = (left as i64, top as i64);
+        let mut upper = self.pixel(left, top);
+        upper *= 1.0 - fx;
+        let mut upper_right = self.pixel(left + 1, top);
+        upper_right *= fx;
+        upper += &upper_right;
+        let mut lower = self.pixel(left, top + 1);
+        lower *= 1.0 - fx;
+        let mut lower_right = self.pixel(left + 1, top + 1);
+        lower_right *= fx;
+        lower += &lower_right;
+
+        upper *= 1.0 - fy;
+        lower *= fy;
+        upper += &lower;
+        return upper;
+    }
+    //The brightness of the image at the given texture coordinates, from 0 to 1, for images used as height maps.
+    pub fn height_at(&self, uv: (f64, f64)) -> f64{
+        let color = self.color_at(uv);
+        return (color.x() + color.y() + color.z()) / 3.0;
+    }
+    //How far apart the centres of neighbouring pixels are in texture coordinates.
+    pub fn pixel_size(&self) -> (f64, f64){
+        return (1.0 / self.width as f64, 1.0 / self.height as f64);
+    }
+}
+
+impl fmt::Display for ImageTexture{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let wrap = match self.wrap {
+            WrapMode::Repeat => "repeated",
+            WrapMode::Clamp => "clamped",
+        };
+        let filter = match self.filter {
+            Filter::Nearest => "nearest pixel",
+            Filter::Bilinear => "bilinear",
+        };
+        return write!(f, "Texture {} of {}x{} pixels, {} with {} filtering", self.filename, self.width, self.height, wrap, filter);
+    }
+}
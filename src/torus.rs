@@ -0,0 +1,230 @@
+use std::fmt;
+use crate::matrix::Vector4;
+use crate::shape::{LightingProps,Shape,Interval,Hit,read_props_from_tokens,intervals_from_crossings};
+use crate::polygon::SpatialProps;
+use crate::bounding_box::BoundingBox;
+use crate::polynomial;
+
+//Collisions closer than this fraction of the torus' size to the start of a ray are ignored,
+//so that rays leaving the surface do not collide with it again due to rounding errors.
+const SELF_COLLISION_EPSILON : f64 = 0.000001;
+//How much larger than the torus the sphere that the search for crossings is limited to is, as a fraction of its radius.
+//The sphere touches the outside of the tube, so without this, crossings there would fall on the ends of the search and could be lost to rounding.
+const SEARCH_SPHERE_PADDING : f64 = 0.000001;
+
+//A torus around the y axis of object space. The center of the tube is a circle of the major radius
+//in the XZ plane and the tube itself has the minor radius.
+pub struct Torus{
+    pub spatial_props: SpatialProps,
+    pub lighting_props: LightingProps,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl Torus{
+    //Reads a TORUS line, which has the major and minor radii after the name and is otherwise laid out like a sphere.
+    pub fn read_from_tokens(tokens: &Vec<&str>) -> Option<Self>{
+        if tokens.len() < 4 {
+            return None;
+        }
+        let major_radius = tokens[2].to_string().trim().parse::<f64>().ok()?;
+        let minor_radius = tokens[3].to_string().trim().parse::<f64>().ok()?;
+        if major_radius <= 0.0 || minor_radius <= 0.0 {
+            return None;
+        }
+
+        let mut shape_tokens = tokens.clone();
+        shape_tokens.drain(2..4);
+        return match read_props_from_tokens(&shape_tokens){
+            Some((spatial_props, lighting_props)) => Some(Self{spatial_props, lighting_props, major_radius, minor_radius}),
+            None => None,
+        }
+    }
+    //Working with a unit direction keeps the quartic's coefficients well scaled.
+    //Returns the unit direction and the length of the ray, so that distances s along it relate to t by s = t * ray_len.
+    fn unit_direction(ray_prime: &Vector4) -> Option<(Vector4, f64)>{
+        let ray_len = ray_prime.len();
+        if ray_len <= 0.0 {
+            return None;
+        }
+        let mut dir = ray_prime.clone();
+        dir /= ray_len;
+        return Some((dir, ray_len));
+    }
+    //Finds the distances s between s_min and s_max at which origin_prime + s * dir crosses the torus,
+    //in order, together with the object space points of the crossings.
+    fn crossings(&self, origin_prime: &Vector4, dir: &Vector4, s_min: f64, s_max: f64) -> Vec<(f64, Vector4)>{
+        //Only search the part of the line inside the sphere that holds the torus.
+        let outer = (self.major_radius + self.minor_radius) * (1.0 + SEARCH_SPHERE_PADDING);
+        let b = origin_prime.dot(dir);
+        let c = origin_prime.dot(origin_prime) - outer * outer;
+        let det = b * b - c;
+        if det < 0.0 {
+            return Vec::new();
+        }
+        let sqrt_det = det.sqrt();
+        let s_min = s_min.max(-b - sqrt_det);
+        let s_max = s_max.min(-b + sqrt_det);
+        if s_min >= s_max {
+            return Vec::new();
+        }
+
+        //Moving the origin up to the sphere avoids the cancellation that distant origins cause.
+        let mut start = dir.clone();
+        start *= s_min;
+        start += origin_prime;
+
+        //(|p|^2 + R^2 - r^2)^2 = 4R^2(x^2 + z^2), with p = start + s * dir.
+        let major_sq = self.major_radius * self.major_radius;
+        let beta = start.dot(dir);
+        let gamma = start.dot(&start) + major_sq - self.minor_radius * self.minor_radius;
+        let flat_dir_sq = dir.x() * dir.x() + dir.z() * dir.z();
+        let flat_dot = start.x() * dir.x() + start.z() * dir.z();
+        let flat_start_sq = start.x() * start.x() + start.z() * start.z();
+        let coeffs = [
+            1.0,
+            4.0 * beta,
+            4.0 * beta * beta + 2.0 * gamma - 4.0 * major_sq * flat_dir_sq,
+            4.0 * beta * gamma - 8.0 * major_sq * flat_dot,
+            gamma * gamma - 4.0 * major_sq * flat_start_sq,
+        ];
+        return polynomial::roots_in(&coeffs, 0.0, s_max - s_min).into_iter().map(|s| {
+            let mut col_pt_prime = dir.clone();
+            col_pt_prime *= s;
+            col_pt_prime += &start;
+            return (s_min + s, col_pt_prime);
+        }).collect();
+    }
+    //The outward object space normal, which points from the nearest point on the circle through the middle of the tube.
+    fn normal_at(&self, col_pt_prime: &Vector4) -> Vector4{
+        let flat_len = (col_pt_prime.x() * col_pt_prime.x() + col_pt_prime.z() * col_pt_prime.z()).sqrt();
+        let mut normal_prime = col_pt_prime.clone();
+        if flat_len > 0.0 {
+            normal_prime.arr[0] -= self.major_radius * col_pt_prime.x() / flat_len;
+            normal_prime.arr[2] -= self.major_radius * col_pt_prime.z() / flat_len;
+        }
+        normal_prime.force_vec();
+        return normal_prime;
+    }
+}
+
+impl Shape for Torus{
+    fn lighting_props(&self) -> &LightingProps{
+        return &self.lighting_props;
+    }
+    fn spatial_props(&self) -> Option<&SpatialProps>{
+        return Some(&self.spatial_props);
+    }
+    fn bounding_box(&self) -> BoundingBox{
+        let outer = self.major_radius + self.minor_radius;
+        let object_bounds = BoundingBox::new(Vector4::point(-outer, -self.minor_radius, -outer), Vector4::point(outer, self.minor_radius, outer));
+        return self.spatial_props.bounding_box(&object_bounds);
+    }
+    fn is_solid(&self) -> bool{
+        return true;
+    }
+    fn check_intervals(&self, origin: &Vector4, ray: &Vector4) -> Vec<Interval>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let (dir, ray_len) = match Self::unit_direction(&(&self.spatial_props.inv_matrix * ray)) {
+            None => return Vec::new(),
+            Some(unit) => unit,
+        };
+        let crossings = self.crossings(&origin_prime, &dir, f64::NEG_INFINITY, f64::INFINITY).into_iter().map(|(s, col_pt_prime)| {
+            return (s / ray_len, self.normal_at(&col_pt_prime).apply_inv_transpose(&self.spatial_props.inv_transp));
+        }).collect();
+        return intervals_from_crossings(crossings, ray);
+    }
+    fn check_collision(&self, origin: &Vector4, ray: &Vector4, min: f64, max: f64) -> Option<Hit>{
+        let origin_prime = &self.spatial_props.inv_matrix * origin;
+        let (dir, ray_len) = Self::unit_direction(&(&self.spatial_props.inv_matrix * ray))?;
+
+        let s_min = (min * ray_len).max(SELF_COLLISION_EPSILON * (self.major_radius + self.minor_radius));
+        let (s, col_pt_prime) = *self.crossings(&origin_prime, &dir, s_min, max * ray_len).first()?;
+        let mut normal_prime = self.normal_at(&col_pt_prime);
+        let front_face = normal_prime.dot(&dir) <= 0.0;
+        if !front_face {
+            normal_prime *= -1.0;
+        }
+
+        let t = s / ray_len;
+        let mut col_pt = ray.clone();
+        col_pt *= t;
+        col_pt += origin;
+        col_pt.force_point();
+        let normal = normal_prime.apply_inv_transpose(&self.spatial_props.inv_transp);
+        //u runs around the y axis and v around the tube, starting from its inner edge.
+        let flat_len = (col_pt_prime.x() * col_pt_prime.x() + col_pt_prime.z() * col_pt_prime.z()).sqrt();
+        let uv = (0.5 + col_pt_prime.x().atan2(col_pt_prime.z()) / (2.0 * std::f64::consts::PI), 0.5 + col_pt_prime.y().atan2(flat_len - self.major_radius) / (2.0 * std::f64::consts::PI));
+        let mut hit = Hit::new(t, col_pt, normal, front_face);
+        hit.set_uv(uv, &Vector4::vec(col_pt_prime.z(), 0.0, -col_pt_prime.x()), &self.spatial_props);
+        return Some(hit);
+    }
+}
+
+impl fmt::Display for Torus{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Torus with radii {} and {}, scale {}, rotation X:{} Y:{} Z:{} and color {} located at {}.\n", self.major_radius, self.minor_radius, self.spatial_props.scale, self.spatial_props.r_x, self.spatial_props.r_y, self.spatial_props.r_z, self.lighting_props.color, self.spatial_props.pos)?;
+        return write!(f, "Lighting coefficients: amb:{} diff:{} spec:{} refl:{} bright:{}.", self.lighting_props.amb, self.lighting_props.diff, self.lighting_props.spec, self.lighting_props.refl, self.lighting_props.bright);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //A torus around the origin with a major radius of 2 and a minor radius of 0.5.
+    fn torus() -> Torus{
+        let tokens: Vec<&str> = "TORUS ring 2 0.5 0 0 0 1 1 1 0 0 0 1 1 1 0.2 0.7 0.5 0 20".split_whitespace().collect();
+        return Torus::read_from_tokens(&tokens).unwrap();
+    }
+    fn assert_close(a: &Vector4, b: &Vector4){
+        assert!((a - b).len() < 0.000001, "{} is not {}", a, b);
+    }
+
+    #[test]
+    fn rays_hit_the_outside_of_the_tube(){
+        let torus = torus();
+        let hit = torus.check_collision(&Vector4::point(0.0, 0.0, 10.0), &Vector4::vec(0.0, 0.0, -1.0), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 7.5).abs() < 0.000001 && hit.front_face);
+        assert_close(&hit.point, &Vector4::point(0.0, 0.0, 2.5));
+        let mut normal = hit.normal;
+        normal.normalize();
+        assert_close(&normal, &Vector4::vec(0.0, 0.0, 1.0));
+
+        //From above, with a ray twice as long, so t is half the distance.
+        let hit = torus.check_collision(&Vector4::point(2.0, 5.0, 0.0), &Vector4::vec(0.0, -2.0, 0.0), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 2.25).abs() < 0.000001);
+        assert_close(&hit.point, &Vector4::point(2.0, 0.5, 0.0));
+        //The hit closest to the ray's start is found, not the first root of the quartic, which lies behind it.
+        let hit = torus.check_collision(&Vector4::point(0.0, 0.0, 0.0), &Vector4::vec(0.0, 0.0, -1.0), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 1.5).abs() < 0.000001);
+    }
+
+    #[test]
+    fn rays_miss_through_the_hole_and_past_the_tube(){
+        let torus = torus();
+        assert!(torus.check_collision(&Vector4::point(0.0, 5.0, 0.0), &Vector4::vec(0.0, -1.0, 0.0), 0.0, f64::INFINITY).is_none());
+        assert!(torus.check_collision(&Vector4::point(-10.0, 0.6, 0.0), &Vector4::vec(1.0, 0.0, 0.0), 0.0, f64::INFINITY).is_none());
+        //The hit lies beyond the end of the ray.
+        assert!(torus.check_collision(&Vector4::point(0.0, 0.0, 10.0), &Vector4::vec(0.0, 0.0, -1.0), 0.0, 7.0).is_none());
+    }
+
+    #[test]
+    fn rays_inside_the_tube_hit_its_back(){
+        let torus = torus();
+        let hit = torus.check_collision(&Vector4::point(2.0, 0.0, 0.0), &Vector4::vec(1.0, 0.0, 0.0), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 0.5).abs() < 0.000001 && !hit.front_face);
+        //The normal is turned to face the ray.
+        assert!(hit.normal.dot(&Vector4::vec(1.0, 0.0, 0.0)) < 0.0);
+    }
+
+    #[test]
+    fn line_through_the_torus_gives_both_sides_of_the_tube(){
+        let intervals = torus().check_intervals(&Vector4::point(0.0, 0.0, 10.0), &Vector4::vec(0.0, 0.0, -1.0));
+        assert_eq!(intervals.len(), 2);
+        let expected = [(7.5, 8.5), (11.5, 12.5)];
+        for (interval, (enter, exit)) in intervals.iter().zip(expected.iter()){
+            assert!((interval.enter.0 - enter).abs() < 0.000001 && (interval.exit.0 - exit).abs() < 0.000001);
+        }
+    }
+}